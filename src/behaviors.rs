@@ -0,0 +1,155 @@
+// -----------------------------------------------------------------------------
+// File: behaviors.rs
+// Description: Data-driven effect behaviour, as read from an effect file.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::effect::{Blast, Contact, DEFAULT_LIFETIME, Effect, EffectBehavior, EffectVisual, Shape};
+use crate::materials::MaterialDef;
+
+/// Represents how the sub-effects of a split are oriented.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitMode {
+    /// Sub-effects come in pairs diverging symmetrically from the effect's direction.
+    Mirrored,
+    /// Each sub-effect gets its own random deviation from the effect's direction.
+    Random,
+}
+
+/// Represents the shape of an effect in an effect file.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShapeKind {
+    /// A disc whose diameter is the width of the effect.
+    Circle,
+    /// A rectangle.
+    Rectangle,
+}
+
+/// The definition of an effect type, as read from an effect file.
+///
+/// # Fields
+///
+/// - `name`: The unique name of the effect, shown on its button.
+/// - `speed`: The speed, in world units per simulated second.
+/// - `damage`: The damage dealt to each touched cell.
+/// - `ignore_durability`: Whether touched cells are destroyed regardless of their durability.
+/// - `lifetime`: The lifetime, in simulated seconds.
+/// - `split_probability`: The probability of splitting at each step, until the effect splits once.
+/// - `split_count`: The number of sub-effects of a split.
+/// - `split_spread`: The maximum angle between a sub-effect and the effect's direction, in radians.
+/// - `split_mode`: How the sub-effects are oriented.
+/// - `blast`: The blast dealt when the effect detonates. An effect with a blast detonates on
+///   the first solid cell it touches instead of damaging it.
+/// - `fuse`: The time after which the effect detonates on its own, in simulated seconds.
+/// - `shape`: The shape of the effect.
+/// - `size`: The width and height of the effect, in cells.
+/// - `color`: The RGB color of the effect.
+/// - `rotate`: Whether the effect is drawn rotated along its direction.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EffectDef {
+    pub name: String,
+    pub speed: f32,
+    #[serde(default)]
+    pub damage: f32,
+    #[serde(default)]
+    pub ignore_durability: bool,
+    #[serde(default = "default_lifetime")]
+    pub lifetime: f32,
+    #[serde(default)]
+    pub split_probability: f64,
+    #[serde(default)]
+    pub split_count: usize,
+    #[serde(default)]
+    pub split_spread: f32,
+    #[serde(default = "default_split_mode")]
+    pub split_mode: SplitMode,
+    #[serde(default)]
+    pub blast: Option<Blast>,
+    #[serde(default)]
+    pub fuse: Option<f32>,
+    pub shape: ShapeKind,
+    pub size: [f32; 2],
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub rotate: bool,
+}
+
+fn default_lifetime() -> f32 {
+    DEFAULT_LIFETIME
+}
+
+fn default_split_mode() -> SplitMode {
+    SplitMode::Random
+}
+
+impl EffectDef {
+    // Draw a random deviation from the effect's direction
+    fn deviation(&self, effect: &mut Effect) -> f32 {
+        if self.split_spread > 0.0 {
+            effect.rng.random_range(-self.split_spread..self.split_spread)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl EffectBehavior for EffectDef {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn lifetime(&self) -> f32 {
+        self.lifetime
+    }
+
+    fn advance(&self, effect: &mut Effect, dt: f32) {
+        effect.move_forward(self.speed, dt);
+    }
+
+    fn on_contact(&self, _effect: &mut Effect, _material: &MaterialDef) -> Contact {
+        match self.blast {
+            Some(blast) => Contact::Detonate(blast),
+            None => Contact::Damage { amount: self.damage, ignore_durability: self.ignore_durability },
+        }
+    }
+
+    fn detonation(&self, effect: &Effect) -> Option<Blast> {
+        let (fuse, blast) = (self.fuse?, self.blast?);
+        (effect.age >= fuse).then_some(blast)
+    }
+
+    fn spawn(&self, effect: &mut Effect) -> Vec<Effect> {
+        if self.split_count == 0 || !effect.rng.random_bool(self.split_probability) {
+            return Vec::new();
+        }
+
+        let mut children = Vec::with_capacity(self.split_count);
+        while children.len() < self.split_count {
+            let offset = self.deviation(effect);
+            let direction = effect.direction;
+            children.push(effect.child(direction + offset));
+            if self.split_mode == SplitMode::Mirrored && children.len() < self.split_count {
+                children.push(effect.child(direction - offset));
+            }
+        }
+        children
+    }
+
+    fn visual(&self) -> EffectVisual {
+        let [width, height] = self.size;
+        let shape = match self.shape {
+            ShapeKind::Circle => Shape::Circle { radius: width / 2.0 },
+            ShapeKind::Rectangle => Shape::Rectangle { width, height },
+        };
+        EffectVisual { shape, color: self.color, rotate: self.rotate }
+    }
+}
@@ -0,0 +1,110 @@
+// -----------------------------------------------------------------------------
+// File: camera.rs
+// Description: Camera over the world, with panning, zooming and conversion
+//              between screen and world coordinates.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use ggez::graphics::Rect;
+
+// Smallest and largest zoom factors
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+
+/// A camera looking at a part of the world, scaled by a zoom factor.
+///
+/// The centre of the screen always stays over the world, so that the world cannot be lost.
+///
+/// # Fields
+/// - `x`: The world position at the left edge of the screen, in world units.
+/// - `y`: The world position at the top edge of the screen, in world units.
+/// - `zoom`: The number of screen pixels per world unit.
+/// - `screen_width`: The width of the screen, in pixels.
+/// - `screen_height`: The height of the screen, in pixels.
+/// - `world_width`: The width of the world, in world units.
+/// - `world_height`: The height of the world, in world units.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    x: f32,
+    y: f32,
+    zoom: f32,
+    screen_width: f32,
+    screen_height: f32,
+    world_width: f32,
+    world_height: f32,
+}
+
+/// # Methods
+///
+/// - `new(screen_width: f32, screen_height: f32, world_width: f32, world_height: f32) -> Self`:
+///   Creates a camera showing the top-left corner of the world at a 1:1 scale.
+/// - `view() -> Rect`:
+///   Returns the part of the world shown on screen, in world units.
+/// - `screen_to_world(x: f32, y: f32) -> (f32, f32)`:
+///   Converts a position on screen, in pixels, to a position in the world, in world units.
+/// - `set_screen_size(width: f32, height: f32)` / `set_world_size(width: f32, height: f32)`:
+///   Follow a resized window, or a resized world.
+/// - `pan(dx: f32, dy: f32)`:
+///   Moves the world on screen by the given number of pixels, e.g. following a drag.
+/// - `zoom_at(x: f32, y: f32, factor: f32)`:
+///   Multiplies the zoom by a factor, keeping the world position under the given screen position in place.
+/// - `scale_world(ratio: f32)`:
+///   Follows a world scaled by a ratio (e.g. a new cell size), staying over the same part of it.
+impl Camera {
+    pub fn new(screen_width: f32, screen_height: f32, world_width: f32, world_height: f32) -> Self {
+        Self { x: 0.0, y: 0.0, zoom: 1.0, screen_width, screen_height, world_width, world_height }
+    }
+
+    pub fn view(&self) -> Rect {
+        Rect::new(self.x, self.y, self.screen_width / self.zoom, self.screen_height / self.zoom)
+    }
+
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + x / self.zoom, self.y + y / self.zoom)
+    }
+
+    pub fn set_screen_size(&mut self, width: f32, height: f32) {
+        self.screen_width = width;
+        self.screen_height = height;
+        self.keep_in_world();
+    }
+
+    pub fn set_world_size(&mut self, width: f32, height: f32) {
+        self.world_width = width;
+        self.world_height = height;
+        self.keep_in_world();
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.x -= dx / self.zoom;
+        self.y -= dy / self.zoom;
+        self.keep_in_world();
+    }
+
+    pub fn zoom_at(&mut self, x: f32, y: f32, factor: f32) {
+        let (world_x, world_y) = self.screen_to_world(x, y);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.x = world_x - x / self.zoom;
+        self.y = world_y - y / self.zoom;
+        self.keep_in_world();
+    }
+
+    pub fn scale_world(&mut self, ratio: f32) {
+        self.x *= ratio;
+        self.y *= ratio;
+        self.world_width *= ratio;
+        self.world_height *= ratio;
+        self.keep_in_world();
+    }
+
+    // Keep the centre of the screen over the world
+    fn keep_in_world(&mut self) {
+        let (half_width, half_height) = (self.screen_width / self.zoom / 2.0, self.screen_height / self.zoom / 2.0);
+        self.x = (self.x + half_width).clamp(0.0, self.world_width) - half_width;
+        self.y = (self.y + half_height).clamp(0.0, self.world_height) - half_height;
+    }
+}
@@ -4,19 +4,19 @@
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: March 15, 2025
-// Last modified: March 25, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
-use crate::materials::Material;
+use crate::materials::MaterialId;
 
 /// Represents a cell with specific properties such as material and durability.
 ///
 /// # Fields
-/// - `material`: The material that the cell is made of.
+/// - `material`: The identifier of the material that the cell is made of.
 /// - `durability`: A floating-point value representing the durability of the cell.
 #[derive(Debug, Clone)]
 pub struct Cell {
-    pub material: Material,
+    pub material: MaterialId,
     pub durability: f32,
 }
\ No newline at end of file
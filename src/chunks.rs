@@ -0,0 +1,366 @@
+// -----------------------------------------------------------------------------
+// File: chunks.rs
+// Description: Chunked storage of the terrain: square chunks of cells loaded
+//              and unloaded independently, each with its own quadtree.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use ggez::graphics::Rect;
+use std::ops::Range;
+
+use crate::cell::Cell;
+use crate::materials::MaterialId;
+use crate::quadtree::{QuadTree, QuadTreeItem};
+
+/// The size of a chunk, in cells.
+pub const CHUNK_SIZE: usize = 64;
+
+// Capacity of the nodes of the chunk quadtrees
+const QUADTREE_CAPACITY: usize = 8;
+
+/// A square block of cells, the chunks on the right and bottom edges of the world being clipped.
+///
+/// # Fields
+/// - `cells`: The cells of the chunk, in column-major order.
+/// - `width`: The width of the chunk, in cells.
+/// - `height`: The height of the chunk, in cells.
+/// - `dirty`: Whether a cell was modified since the chunk was loaded. Dirty chunks are never unloaded,
+///   since they could not be generated again.
+/// - `changed`: Whether a cell was modified since the quadtree was last built.
+/// - `settling`: Whether the chunk was generated with liquid or granular cells, and no pass has run on it yet.
+/// - `quadtree`: A quadtree of the non-air cells of the chunk, for spatial queries.
+struct Chunk {
+    cells: Vec<Cell>,
+    width: usize,
+    height: usize,
+    dirty: bool,
+    changed: bool,
+    settling: bool,
+    quadtree: QuadTree,
+}
+
+/// The part of the terrain the cellular-automaton passes work on, fixed at the start of a pass.
+///
+/// Only the cells of the modified chunks and of the settling ones move: a chunk generated with liquid or
+/// granular cells is swept once, the chunks whose cells moved becoming modified, and the others holding
+/// the terrain as generated, found at rest. They can reach the chunks around them, which must be loaded,
+/// and nothing further, whether it is loaded or not, so that the passes do not depend on the chunks the view
+/// keeps loaded beyond the ones they sweep.
+///
+/// # Fields
+/// - `modified`: Whether each chunk was modified or is settling, row by row.
+/// - `reachable`: Whether each chunk is modified, settling, or next to such a chunk, row by row.
+/// - `chunks_x`: The number of chunks along the x axis.
+/// - `width`: The width of the world, in cells.
+/// - `height`: The height of the world, in cells.
+pub struct ActiveRegion {
+    modified: Vec<bool>,
+    reachable: Vec<bool>,
+    chunks_x: usize,
+    width: usize,
+    height: usize,
+}
+
+/// # Methods
+///
+/// - `spans(y: usize) -> Vec<Range<usize>>`:
+///   Returns the columns of the cells of a row within modified or settling chunks, from left to right.
+/// - `contains(x: usize, y: usize) -> bool`:
+///   Tells whether a cell is within reach of the pass.
+impl ActiveRegion {
+    pub fn spans(&self, y: usize) -> Vec<Range<usize>> {
+        let mut spans: Vec<Range<usize>> = Vec::new();
+        if y >= self.height {
+            return spans;
+        }
+        for cx in 0..self.chunks_x {
+            if !self.modified[(y / CHUNK_SIZE) * self.chunks_x + cx] {
+                continue;
+            }
+            // Merge the spans of neighbouring chunks
+            let columns = cx * CHUNK_SIZE..((cx + 1) * CHUNK_SIZE).min(self.width);
+            match spans.last_mut() {
+                Some(span) if span.end == columns.start => span.end = columns.end,
+                _ => spans.push(columns),
+            }
+        }
+        spans
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.reachable[(y / CHUNK_SIZE) * self.chunks_x + x / CHUNK_SIZE]
+    }
+}
+
+/// The terrain of the world, split into chunks of `CHUNK_SIZE` cells that are loaded on demand.
+///
+/// Cells are addressed with their world coordinates, `y` growing downwards. Cells of unloaded chunks
+/// cannot be read nor written: `get` returns `None` for them, as for cells outside the world.
+///
+/// # Fields
+/// - `chunks`: The chunks, loaded or not, row by row.
+/// - `width`: The width of the world, in cells.
+/// - `height`: The height of the world, in cells.
+/// - `cell_size`: The size of a cell, in world units, giving the coordinates of the quadtree items.
+pub struct ChunkedTerrain {
+    chunks: Vec<Option<Chunk>>,
+    width: usize,
+    height: usize,
+    cell_size: f32,
+}
+
+/// # Methods
+///
+/// - `new(width: usize, height: usize, cell_size: f32) -> Self`:
+///   Creates a world with no chunk loaded.
+/// - `width() -> usize` / `height() -> usize`:
+///   Return the dimensions of the world, in cells.
+/// - `chunks_x() -> usize` / `chunks_y() -> usize`:
+///   Return the number of chunks along each axis.
+/// - `chunk_bounds(cx: usize, cy: usize) -> (Range<usize>, Range<usize>)`:
+///   Returns the columns and rows of the cells of a chunk.
+/// - `is_loaded(cx: usize, cy: usize) -> bool` / `is_dirty(cx: usize, cy: usize) -> bool`:
+///   Tell whether a chunk is loaded, and whether it was modified since.
+/// - `loaded() -> Vec<(usize, usize)>`:
+///   Returns the coordinates of the loaded chunks, column by column.
+/// - `insert(cx: usize, cy: usize, cells: Vec<Cell>, dirty: bool)`:
+///   Loads a chunk from its cells, in column-major order, and builds its quadtree.
+/// - `mark_settling(cx: usize, cy: usize)`:
+///   Marks a chunk generated with liquid or granular cells, so that the next passes sweep it.
+/// - `end_settling()`:
+///   Ends the settling of the chunks swept by the passes, those whose cells moved being dirty.
+/// - `unload(cx: usize, cy: usize)` / `clear()`:
+///   Unloads a chunk, or every chunk.
+/// - `get(x: usize, y: usize) -> Option<&Cell>`:
+///   Returns a cell, if its chunk is loaded.
+/// - `set(x: usize, y: usize, cell: Cell) -> Option<Cell>`:
+///   Replaces a cell of a loaded chunk, marking the chunk as dirty, and returns the previous cell.
+/// - `get_mut(x: usize, y: usize) -> Option<&mut Cell>`:
+///   Returns a cell to modify, marking its chunk as dirty.
+/// - `modified_surroundings() -> Vec<(Range<usize>, Range<usize>)>`:
+///   Returns the chunks around each modified or settling chunk, itself included, which the passes may reach.
+/// - `active_region() -> ActiveRegion`:
+///   Returns the part of the terrain the passes work on, from the chunks modified so far and the settling ones.
+/// - `set_cell_size(cell_size: f32)`:
+///   Changes the size of a cell, rebuilding the quadtrees of the loaded chunks.
+/// - `update_quadtrees()`:
+///   Rebuilds the quadtrees of the chunks modified since they were built.
+/// - `query(range: Rect) -> Vec<QuadTreeItem>`:
+///   Returns the non-air cells within a range, in world units, from the quadtrees of the loaded chunks.
+impl ChunkedTerrain {
+    pub fn new(width: usize, height: usize, cell_size: f32) -> Self {
+        let count = width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE);
+        Self {
+            chunks: (0..count).map(|_| None).collect(),
+            width,
+            height,
+            cell_size,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn chunks_x(&self) -> usize {
+        self.width.div_ceil(CHUNK_SIZE)
+    }
+
+    pub fn chunks_y(&self) -> usize {
+        self.height.div_ceil(CHUNK_SIZE)
+    }
+
+    pub fn chunk_bounds(&self, cx: usize, cy: usize) -> (Range<usize>, Range<usize>) {
+        let (x, y) = (cx * CHUNK_SIZE, cy * CHUNK_SIZE);
+        (x..(x + CHUNK_SIZE).min(self.width), y..(y + CHUNK_SIZE).min(self.height))
+    }
+
+    pub fn is_loaded(&self, cx: usize, cy: usize) -> bool {
+        self.chunk(cx, cy).is_some()
+    }
+
+    pub fn is_dirty(&self, cx: usize, cy: usize) -> bool {
+        self.chunk(cx, cy).is_some_and(|chunk| chunk.dirty)
+    }
+
+    pub fn loaded(&self) -> Vec<(usize, usize)> {
+        let mut loaded = Vec::new();
+        for cx in 0..self.chunks_x() {
+            for cy in 0..self.chunks_y() {
+                if self.is_loaded(cx, cy) {
+                    loaded.push((cx, cy));
+                }
+            }
+        }
+        loaded
+    }
+
+    pub fn insert(&mut self, cx: usize, cy: usize, cells: Vec<Cell>, dirty: bool) {
+        let (columns, rows) = self.chunk_bounds(cx, cy);
+        let mut chunk = Chunk {
+            cells,
+            width: columns.len(),
+            height: rows.len(),
+            dirty,
+            changed: true,
+            settling: false,
+            quadtree: QuadTree::new(Rect::default(), QUADTREE_CAPACITY),
+        };
+        self.build_quadtree(cx, cy, &mut chunk);
+        let index = self.index(cx, cy);
+        self.chunks[index] = Some(chunk);
+    }
+
+    pub fn mark_settling(&mut self, cx: usize, cy: usize) {
+        let index = self.index(cx, cy);
+        if let Some(chunk) = self.chunks[index].as_mut() {
+            chunk.settling = true;
+        }
+    }
+
+    pub fn end_settling(&mut self) {
+        self.chunks.iter_mut().flatten().for_each(|chunk| chunk.settling = false);
+    }
+
+    pub fn unload(&mut self, cx: usize, cy: usize) {
+        let index = self.index(cx, cy);
+        self.chunks[index] = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.iter_mut().for_each(|chunk| *chunk = None);
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let chunk = self.chunk(x / CHUNK_SIZE, y / CHUNK_SIZE)?;
+        chunk.cells.get((x % CHUNK_SIZE) * chunk.height + y % CHUNK_SIZE)
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) -> Option<Cell> {
+        self.get_mut(x, y).map(|target| std::mem::replace(target, cell))
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = self.index(x / CHUNK_SIZE, y / CHUNK_SIZE);
+        let chunk = self.chunks[index].as_mut()?;
+        chunk.dirty = true;
+        chunk.changed = true;
+        chunk.cells.get_mut((x % CHUNK_SIZE) * chunk.height + y % CHUNK_SIZE)
+    }
+
+    pub fn modified_surroundings(&self) -> Vec<(Range<usize>, Range<usize>)> {
+        self.loaded().into_iter()
+            .filter(|&(cx, cy)| self.is_active(cx, cy))
+            .map(|(cx, cy)| (cx.saturating_sub(1)..(cx + 2).min(self.chunks_x()), cy.saturating_sub(1)..(cy + 2).min(self.chunks_y())))
+            .collect()
+    }
+
+    pub fn active_region(&self) -> ActiveRegion {
+        let modified: Vec<bool> = self.chunks.iter().map(|chunk| chunk.as_ref().is_some_and(|chunk| chunk.dirty || chunk.settling)).collect();
+        let mut reachable = vec![false; modified.len()];
+        for (chunk_columns, chunk_rows) in self.modified_surroundings() {
+            for cx in chunk_columns {
+                for cy in chunk_rows.clone() {
+                    reachable[self.index(cx, cy)] = true;
+                }
+            }
+        }
+        ActiveRegion { modified, reachable, chunks_x: self.chunks_x(), width: self.width, height: self.height }
+    }
+
+    pub fn set_cell_size(&mut self, cell_size: f32) {
+        self.cell_size = cell_size;
+        self.chunks.iter_mut().flatten().for_each(|chunk| chunk.changed = true);
+        self.update_quadtrees();
+    }
+
+    pub fn update_quadtrees(&mut self) {
+        for index in 0..self.chunks.len() {
+            if let Some(mut chunk) = self.chunks[index].take() {
+                if chunk.changed {
+                    let cx = index % self.chunks_x();
+                    let cy = index / self.chunks_x();
+                    self.build_quadtree(cx, cy, &mut chunk);
+                }
+                self.chunks[index] = Some(chunk);
+            }
+        }
+    }
+
+    pub fn query(&self, range: Rect) -> Vec<QuadTreeItem> {
+        let mut found = Vec::new();
+        if self.width == 0 || self.height == 0 {
+            return found;
+        }
+
+        // Chunks overlapping the range, clamped to the world
+        let chunk_extent = CHUNK_SIZE as f32 * self.cell_size;
+        let first = |start: f32| (start / chunk_extent).max(0.0) as usize;
+        let last = |end: f32, count: usize| ((end / chunk_extent).max(0.0) as usize).min(count - 1);
+        for cx in first(range.x)..=last(range.x + range.w, self.chunks_x()) {
+            for cy in first(range.y)..=last(range.y + range.h, self.chunks_y()) {
+                if let Some(chunk) = self.chunk(cx, cy) {
+                    found.extend(chunk.quadtree.query(range));
+                }
+            }
+        }
+        found
+    }
+
+    // Index of a chunk in the chunk list
+    fn index(&self, cx: usize, cy: usize) -> usize {
+        cy * self.chunks_x() + cx
+    }
+
+    // The chunk at the given chunk coordinates, if loaded
+    fn chunk(&self, cx: usize, cy: usize) -> Option<&Chunk> {
+        if cx >= self.chunks_x() || cy >= self.chunks_y() {
+            return None;
+        }
+        self.chunks[self.index(cx, cy)].as_ref()
+    }
+
+    // Whether the passes sweep the cells of a chunk
+    fn is_active(&self, cx: usize, cy: usize) -> bool {
+        self.chunk(cx, cy).is_some_and(|chunk| chunk.dirty || chunk.settling)
+    }
+
+    // Rebuild the quadtree of a chunk from its non-air cells
+    fn build_quadtree(&self, cx: usize, cy: usize, chunk: &mut Chunk) {
+        let (columns, rows) = self.chunk_bounds(cx, cy);
+        let boundary = Rect::new(
+            columns.start as f32 * self.cell_size,
+            rows.start as f32 * self.cell_size,
+            chunk.width as f32 * self.cell_size,
+            chunk.height as f32 * self.cell_size,
+        );
+        chunk.quadtree = QuadTree::new(boundary, QUADTREE_CAPACITY);
+
+        for (i, cell) in chunk.cells.iter().enumerate() {
+            if cell.material == MaterialId::AIR {
+                continue;
+            }
+
+            // Insert the cell with its centre in world units
+            let (tx, ty) = (columns.start + i / chunk.height, rows.start + i % chunk.height);
+            let x = tx as f32 * self.cell_size + self.cell_size / 2.0;
+            let y = ty as f32 * self.cell_size + self.cell_size / 2.0;
+            chunk.quadtree.insert(QuadTreeItem { x, y, tx, ty });
+        }
+        chunk.changed = false;
+    }
+}
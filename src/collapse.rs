@@ -0,0 +1,221 @@
+// -----------------------------------------------------------------------------
+// File: collapse.rs
+// Description: Structural integrity of the terrain: detection of unsupported
+//              chunks and the rigid debris they turn into while falling.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+
+use crate::cell::Cell;
+use crate::chunks::{CHUNK_SIZE, ChunkedTerrain};
+use crate::materials::{MaterialId, MaterialRegistry};
+
+// Gravity applied to falling debris, in cells per second squared
+const GRAVITY: f32 = 40.0;
+
+// Largest number of cells of a chunk of terrain that can fall, larger ones being taken as supported
+const MAX_ISLAND_CELLS: usize = 4 * CHUNK_SIZE * CHUNK_SIZE;
+
+/// The coordinates of the cells of a chunk of terrain cut loose from the ground.
+pub type Island = Vec<(usize, usize)>;
+
+/// A rigid chunk of terrain falling down until it lands back into the grid.
+///
+/// # Fields
+/// - `x`: The column of the chunk's origin, in cells.
+/// - `y`: The row of the chunk's origin, in cells (fractional while falling).
+/// - `velocity`: The falling speed, in cells per second.
+/// - `cells`: The cells of the chunk, as `(dx, dy, cell)` offsets from the origin.
+#[derive(Debug, Clone)]
+pub struct Debris {
+    pub x: usize,
+    pub y: f32,
+    pub velocity: f32,
+    pub cells: Vec<(usize, usize, Cell)>,
+}
+
+impl Debris {
+    /// Builds a debris from a set of terrain cells, removing them from the terrain.
+    ///
+    /// # Parameters
+    /// - `terrain`: The terrain the cells are taken from.
+    /// - `positions`: The coordinates of the cells forming the chunk, all loaded.
+    pub fn detach(terrain: &mut ChunkedTerrain, positions: &[(usize, usize)]) -> Self {
+        let x = positions.iter().map(|p| p.0).min().unwrap_or(0);
+        let y = positions.iter().map(|p| p.1).min().unwrap_or(0);
+        let air = Cell { material: MaterialId::AIR, durability: 0.0 };
+        let cells = positions.iter()
+            .filter_map(|&(cx, cy)| terrain.set(cx, cy, air.clone()).map(|cell| (cx - x, cy - y, cell)))
+            .collect();
+        Self { x, y: y as f32, velocity: 0.0, cells }
+    }
+
+    /// Returns the columns and rows of the cells the debris may cover while falling for `dt` seconds.
+    /// Their chunks should be loaded before the debris falls.
+    pub fn reach(&self, dt: f32) -> (Range<usize>, Range<usize>) {
+        let width = self.cells.iter().map(|&(dx, _, _)| dx + 1).max().unwrap_or(1);
+        let height = self.cells.iter().map(|&(_, dy, _)| dy + 1).max().unwrap_or(1);
+        let target = self.y + (self.velocity + GRAVITY * dt) * dt;
+        (self.x..self.x + width, self.y.floor() as usize..target.floor() as usize + height)
+    }
+
+    /// Makes the debris fall for `dt` seconds.
+    ///
+    /// # Returns
+    /// `true` if the debris landed on the terrain or on the bottom edge, in which case
+    /// its origin is snapped to the last free row and it should be settled. The chunks within its `reach`
+    /// must be loaded, since unloaded chunks stop the debris like solid ground.
+    pub fn fall(&mut self, terrain: &ChunkedTerrain, dt: f32) -> bool {
+        self.velocity += GRAVITY * dt;
+        let target = self.y + self.velocity * dt;
+
+        // Check every row crossed during this step
+        let current_row = self.y.floor() as usize;
+        for row in current_row + 1..=target.floor() as usize {
+            if !self.fits(terrain, row) {
+                self.y = (row - 1) as f32;
+                return true;
+            }
+        }
+        self.y = target;
+        false
+    }
+
+    /// Writes the cells of the debris back into the terrain at its current row.
+    ///
+    /// A cell landing on an occupied cell (e.g. other debris settled in the meantime) is stacked on top of
+    /// the cells of the debris in its column, if the cell right above them is air. Otherwise it is lost, rather
+    /// than moved up through the terrain into a cave above it.
+    ///
+    /// # Returns
+    /// The coordinates of every cell written.
+    pub fn settle(self, terrain: &mut ChunkedTerrain) -> Vec<(usize, usize)> {
+        let row = self.y.floor() as usize;
+        let mut changed = Vec::with_capacity(self.cells.len());
+
+        let is_air = |terrain: &ChunkedTerrain, x: usize, y: usize| terrain.get(x, y).is_some_and(|target| target.material == MaterialId::AIR);
+
+        let mut blocked = Vec::new();
+        for (dx, dy, cell) in self.cells {
+            let (x, y) = (self.x + dx, row + dy);
+            if is_air(terrain, x, y) {
+                terrain.set(x, y, cell);
+                changed.push((x, y));
+            } else {
+                blocked.push((x, cell));
+            }
+        }
+
+        // Stack the blocked cells on top of the debris, never through the terrain
+        for (x, cell) in blocked {
+            let top = changed.iter().filter(|&&(cx, _)| cx == x).map(|&(_, y)| y).min();
+            if let Some(y) = top.and_then(|top| top.checked_sub(1))
+                && is_air(terrain, x, y)
+            {
+                terrain.set(x, y, cell);
+                changed.push((x, y));
+            }
+        }
+        changed
+    }
+
+    // Whether every cell of the debris is free when its origin is at `row`
+    fn fits(&self, terrain: &ChunkedTerrain, row: usize) -> bool {
+        self.cells.iter().all(|&(dx, dy, _)| {
+            terrain.get(self.x + dx, row + dy).is_some_and(|cell| cell.material == MaterialId::AIR)
+        })
+    }
+}
+
+/// Finds the chunks of terrain that are no longer supported.
+///
+/// Starting from each seed (typically the neighbours of destroyed cells), a flood fill
+/// collects the structural cells connected to it. A chunk is supported if it reaches the
+/// bottom row, an indestructible cell, or a structural cell of a chunk of the grid never modified, at which
+/// point its search stops. Air, liquid and granular cells are not structural.
+///
+/// The terrain as generated is taken as supported, so the search stays within the modified chunks and
+/// does not depend on the chunks the view keeps loaded, as long as the chunks around the modified ones are
+/// loaded. A chunk larger than `MAX_ISLAND_CELLS` is taken as supported too, and every cell is searched at
+/// most once per call, bounding the work of a step.
+///
+/// # Parameters
+/// - `terrain`: The chunked terrain.
+/// - `materials`: The material registry.
+/// - `seeds`: The cells from which chunks are searched.
+///
+/// # Returns
+/// The cells of every unsupported chunk.
+pub fn find_unsupported(terrain: &ChunkedTerrain, materials: &MaterialRegistry, seeds: &[(usize, usize)]) -> Vec<Island> {
+    let height = terrain.height();
+    let is_structural = |x: usize, y: usize| {
+        terrain.get(x, y).is_some_and(|cell| {
+            let material = materials.get(cell.material);
+            cell.material != MaterialId::AIR && !material.liquid && !material.granular
+        })
+    };
+    let is_modified = |x: usize, y: usize| terrain.is_dirty(x / CHUNK_SIZE, y / CHUNK_SIZE);
+
+    // The search each cell was reached by
+    let mut visited = HashMap::new();
+    let mut islands = Vec::new();
+
+    for (search, &(sx, sy)) in seeds.iter().enumerate() {
+        if visited.contains_key(&(sx, sy)) || !is_structural(sx, sy) || !is_modified(sx, sy) {
+            continue;
+        }
+
+        // Flood fill the chunk containing the seed
+        let mut chunk = Vec::new();
+        let mut anchored = false;
+        let mut queue = VecDeque::from([(sx, sy)]);
+        visited.insert((sx, sy), search);
+        'search: while let Some((x, y)) = queue.pop_front() {
+            chunk.push((x, y));
+            if y + 1 == height || chunk.len() > MAX_ISLAND_CELLS
+                || terrain.get(x, y).is_some_and(|cell| materials.get(cell.material).indestructible) {
+                anchored = true;
+                break;
+            }
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbours {
+                if !is_structural(nx, ny) {
+                    continue;
+                }
+                // Cells reached by an earlier search belong to a supported chunk, since unsupported ones are searched whole
+                match visited.get(&(nx, ny)) {
+                    Some(&other) if other != search => {
+                        anchored = true;
+                        break 'search;
+                    }
+                    Some(_) => {}
+                    None if !is_modified(nx, ny) => {
+                        anchored = true;
+                        break 'search;
+                    }
+                    None => {
+                        visited.insert((nx, ny), search);
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+        }
+
+        if !anchored {
+            islands.push(chunk);
+        }
+    }
+
+    islands
+}
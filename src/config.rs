@@ -0,0 +1,287 @@
+// -----------------------------------------------------------------------------
+// File: config.rs
+// Description: Settings of a world and of the window showing it, given to the
+//              simulation and the game state instead of global constants, and
+//              configuration files presetting them.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::generationmodes::GenerationMode;
+use crate::noisegenerator::NoiseParams;
+use crate::noisetypes::NoiseType;
+
+/// Fields of definitions replaced by a configuration file, keyed by the name of the definition.
+pub type Overrides = BTreeMap<String, toml::Table>;
+
+/// The settings of a world and of the window showing it.
+///
+/// Each simulation is built from its own configuration, so that worlds with different settings
+/// can live side by side in one process.
+///
+/// # Fields
+/// - `screen_width`: The width of the window, in pixels.
+/// - `screen_height`: The height of the window, in pixels.
+/// - `delta`: The fixed time step of the simulation, in seconds.
+/// - `terrain_width`: The width of the terrain, in cells.
+/// - `terrain_height`: The height of the terrain, in cells.
+/// - `cell_size`: The size of a cell, in world units (pixels).
+/// - `seed`: The seed used for terrain generation (`-1` for a random seed).
+/// - `mode`: How the generated terrain is laid out.
+/// - `noise_type`: The noise used for terrain generation.
+/// - `noise_params`: The parameters shaping the noise.
+/// - `falling_sand`: Whether granular materials fall and slide.
+/// - `collapse`: Whether unsupported chunks of terrain collapse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub screen_width: f32,
+    pub screen_height: f32,
+    pub delta: f32,
+    pub terrain_width: usize,
+    pub terrain_height: usize,
+    pub cell_size: f32,
+    pub seed: i64,
+    pub mode: GenerationMode,
+    pub noise_type: NoiseType,
+    pub noise_params: NoiseParams,
+    pub falling_sand: bool,
+    pub collapse: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            screen_width: 700.0,
+            screen_height: 500.0,
+            delta: 1.0 / 15.0,
+            terrain_width: 140,
+            terrain_height: 100,
+            cell_size: 5.0,
+            seed: -1,
+            mode: GenerationMode::Field,
+            noise_type: NoiseType::Perlin,
+            noise_params: NoiseParams::default(),
+            falling_sand: false,
+            collapse: false,
+        }
+    }
+}
+
+/// The settings of a configuration file, named after the command-line flags.
+///
+/// Every setting is optional, an unset one taking the value of the next source: the command-line flags,
+/// gathered the same way, override the file, which overrides the defaults. Paths are relative to the working directory,
+/// as on the command line.
+///
+/// # Fields
+/// - `width` / `height`: The size of the window, in pixels.
+/// - `delta`: The number of simulation steps per second.
+/// - `cellsize`: The size of a cell, in pixels.
+/// - `world_width` / `world_height`: The size of the world, in cells.
+/// - `seed`: The seed for terrain generation (`-1` for a random seed).
+/// - `noise`: The noise used for terrain generation.
+/// - `mode`: How the generated terrain is laid out.
+/// - `frequency`, `octaves`, `lacunarity`, `persistence`: The parameters of the noise.
+/// - `noise_graph`: The noise graph definitions file, used instead of the single noise.
+/// - `falling_sand`: Whether granular materials fall and slide.
+/// - `collapse`: Whether unsupported chunks of terrain collapse.
+/// - `materials`: The material definitions file.
+/// - `effects`: The effect definitions file.
+/// - `layers`: The terrain layer definitions file.
+/// - `material_overrides`: Fields of the materials replacing the ones of the material definitions file, keyed by material
+///   name (e.g. `[material_overrides.rock]`).
+/// - `effect_overrides`: Fields of the effects replacing the ones of the effect definitions file, keyed by effect name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub delta: Option<u32>,
+    pub cellsize: Option<f32>,
+    pub world_width: Option<usize>,
+    pub world_height: Option<usize>,
+    pub seed: Option<i64>,
+    pub noise: Option<NoiseType>,
+    pub mode: Option<GenerationMode>,
+    pub frequency: Option<f64>,
+    pub octaves: Option<usize>,
+    pub lacunarity: Option<f64>,
+    pub persistence: Option<f64>,
+    pub noise_graph: Option<String>,
+    pub falling_sand: Option<bool>,
+    pub collapse: Option<bool>,
+    pub materials: Option<String>,
+    pub effects: Option<String>,
+    pub layers: Option<String>,
+    pub material_overrides: Option<Overrides>,
+    pub effect_overrides: Option<Overrides>,
+}
+
+/// # Methods
+///
+/// - `from_file(path: &str) -> Result<Self, String>`:
+///   Loads the settings from a TOML configuration file.
+/// - `parse(source: &str) -> Result<Self, String>`:
+///   Parses the settings from the contents of a TOML configuration file.
+/// - `save(path: &str) -> Result<(), String>`:
+///   Writes the settings that are set to a TOML configuration file.
+/// - `to_toml() -> Result<String, String>`:
+///   Returns the settings that are set, in the TOML format.
+/// - `merge(overrides: ConfigFile) -> ConfigFile`:
+///   Returns the settings, replaced by the ones set in `overrides`.
+impl ConfigFile {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::parse(&source).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        toml::from_str(source).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let source = self.to_toml()?;
+        fs::write(path, source).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    pub fn merge(self, overrides: ConfigFile) -> ConfigFile {
+        ConfigFile {
+            width: overrides.width.or(self.width),
+            height: overrides.height.or(self.height),
+            delta: overrides.delta.or(self.delta),
+            cellsize: overrides.cellsize.or(self.cellsize),
+            world_width: overrides.world_width.or(self.world_width),
+            world_height: overrides.world_height.or(self.world_height),
+            seed: overrides.seed.or(self.seed),
+            noise: overrides.noise.or(self.noise),
+            mode: overrides.mode.or(self.mode),
+            frequency: overrides.frequency.or(self.frequency),
+            octaves: overrides.octaves.or(self.octaves),
+            lacunarity: overrides.lacunarity.or(self.lacunarity),
+            persistence: overrides.persistence.or(self.persistence),
+            noise_graph: overrides.noise_graph.or(self.noise_graph),
+            falling_sand: overrides.falling_sand.or(self.falling_sand),
+            collapse: overrides.collapse.or(self.collapse),
+            materials: overrides.materials.or(self.materials),
+            effects: overrides.effects.or(self.effects),
+            layers: overrides.layers.or(self.layers),
+            material_overrides: overrides.material_overrides.or(self.material_overrides),
+            effect_overrides: overrides.effect_overrides.or(self.effect_overrides),
+        }
+    }
+}
+
+/// Replaces fields of the definitions of a TOML definitions file.
+///
+/// The definitions are the `[[list]]` tables of the file, found by their `name`. Nested tables (e.g. the blast of an effect)
+/// are merged field by field, and every other field is replaced.
+///
+/// # Parameters
+/// - `source`: The contents of the definitions file.
+/// - `list`: The name of the tables of the definitions (e.g. `material`).
+/// - `overrides`: The fields to replace, keyed by the name of the definition.
+///
+/// # Returns
+/// The contents of the definitions file with the fields replaced, or an error if a definition is unknown or an override
+/// renames it.
+pub fn apply_overrides(source: &str, list: &str, overrides: &Overrides) -> Result<String, String> {
+    if overrides.is_empty() {
+        return Ok(source.to_string());
+    }
+
+    let mut file: toml::Table = toml::from_str(source).map_err(|e| e.to_string())?;
+    for (name, fields) in overrides {
+        // Definitions are looked up by name, so it must stay the same
+        if fields.contains_key("name") {
+            return Err(format!("{} \"{}\": the name cannot be overridden", list, name));
+        }
+
+        let definition = file
+            .get_mut(list)
+            .and_then(|definitions| definitions.as_array_mut())
+            .and_then(|definitions| definitions.iter_mut().filter_map(|d| d.as_table_mut()).find(|d| d.get("name").and_then(|n| n.as_str()) == Some(name)))
+            .ok_or(format!("unknown {} \"{}\"", list, name))?;
+        merge_tables(definition, fields);
+    }
+    toml::to_string(&file).map_err(|e| e.to_string())
+}
+
+// Replace the fields of a table, merging the nested tables
+fn merge_tables(target: &mut toml::Table, fields: &toml::Table) {
+    for (key, value) in fields {
+        match (target.get_mut(key), value) {
+            (Some(toml::Value::Table(inner)), toml::Value::Table(fields)) => merge_tables(inner, fields),
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::EffectRegistry;
+    use crate::materials::MaterialRegistry;
+
+    #[test]
+    fn flags_override_the_file() {
+        let file = ConfigFile::parse("seed = 4\ncellsize = 3.0\nfalling_sand = true\n").unwrap();
+        let flags = ConfigFile { seed: Some(8), falling_sand: Some(false), collapse: Some(true), ..ConfigFile::default() };
+        let merged = file.merge(flags);
+
+        assert_eq!(merged.seed, Some(8));
+        assert_eq!(merged.cellsize, Some(3.0));
+        assert_eq!(merged.falling_sand, Some(false));
+        assert_eq!(merged.collapse, Some(true));
+        assert_eq!(merged.noise, None);
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = ConfigFile::parse("world_width = 300\nnoise = \"fbm\"\nmode = \"sidescroller\"\n\n[material_overrides.rock]\ndurability = 80.0\n").unwrap();
+        assert_eq!(ConfigFile::parse(&settings.to_toml().unwrap()).unwrap(), settings);
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(ConfigFile::parse("cell_size = 3.0\n").is_err());
+        assert!(ConfigFile::parse("seed = \"four\"\n").is_err());
+    }
+
+    #[test]
+    fn overrides_replace_fields() {
+        let overrides = Overrides::from([("rock".to_string(), toml::Table::from_iter([("durability".to_string(), toml::Value::Float(80.0))]))]);
+        let materials = MaterialRegistry::builtin(&overrides).unwrap();
+        assert_eq!(materials.get(materials.id("rock").unwrap()).durability, 80.0);
+
+        // Nested tables are merged field by field
+        let source = "[[effect]]\nname = \"Boom\"\nblast = { radius = 3.0, damage = 9.0 }\n";
+        let blast = toml::Table::from_iter([("radius".to_string(), toml::Value::Float(1.5))]);
+        let overrides = Overrides::from([("Boom".to_string(), toml::Table::from_iter([("blast".to_string(), toml::Value::Table(blast))]))]);
+        let merged: toml::Table = toml::from_str(&apply_overrides(source, "effect", &overrides).unwrap()).unwrap();
+        let merged = &merged["effect"][0]["blast"];
+        assert_eq!((merged["radius"].as_float(), merged["damage"].as_float()), (Some(1.5), Some(9.0)));
+        assert!(EffectRegistry::builtin(&Overrides::from([("Explosion".to_string(), toml::Table::new())])).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_overrides() {
+        let fields = |key: &str| toml::Table::from_iter([(key.to_string(), toml::Value::Float(1.0))]);
+        let unknown = Overrides::from([("marble".to_string(), fields("durability"))]);
+        assert_eq!(MaterialRegistry::builtin(&unknown).unwrap_err(), "unknown material \"marble\"");
+        let renamed = Overrides::from([("rock".to_string(), fields("name"))]);
+        assert_eq!(MaterialRegistry::builtin(&renamed).unwrap_err(), "material \"rock\": the name cannot be overridden");
+    }
+}
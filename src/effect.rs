@@ -1,43 +1,365 @@
 // -----------------------------------------------------------------------------
-// File: effects.rs
-// Description: Defines visual effects and their properties.
+// File: effect.rs
+// Description: Defines the effects, the `EffectBehavior` trait giving each effect type its
+//              behaviour, and the registry of the effect types loaded from the effect file.
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: March 15, 2025
-// Last modified: March 25, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
-use std::time::Instant;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::fs;
+use std::sync::Arc;
 
-/// Represents the type of visual effect in the game.
+use crate::behaviors::EffectDef;
+use crate::config::{Overrides, apply_overrides};
+use crate::materials::MaterialDef;
+
+// Built-in effect definitions, used when no effect file can be loaded
+const DEFAULT_EFFECTS: &str = include_str!("../resources/effects.toml");
+
+/// The default lifetime of an effect, in simulated seconds.
+pub const DEFAULT_LIFETIME: f32 = 3.0;
+
+/// A compact identifier of an effect type, indexing into an `EffectRegistry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EffectId(pub u8);
+
+/// Represents what happens when an effect touches a solid terrain cell.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Contact {
+    /// The effect goes through the cell.
+    Pass,
+    /// The effect damages the cell, and bounces off it if the cell survives.
+    Damage {
+        /// The damage dealt to the cell.
+        amount: f32,
+        /// Whether the cell is destroyed regardless of its durability.
+        ignore_durability: bool,
+    },
+    /// The effect detonates, dealing the given blast around it.
+    Detonate(Blast),
+}
+
+/// Represents the shape of an effect on screen, in cells.
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub enum EffectType {
-    /// A bubble effect.
-    Bubbles,
-    /// A more intense bubble effect.
-    MoreBubbles,
-    /// A lightning effect.
-    Lightning,
+pub enum Shape {
+    /// A disc of the given radius.
+    Circle { radius: f32 },
+    /// A rectangle centred on the effect.
+    Rectangle { width: f32, height: f32 },
+}
+
+/// Describes how a front-end should draw an effect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EffectVisual {
+    /// The shape of the effect.
+    pub shape: Shape,
+    /// The RGB color of the effect.
+    pub color: [u8; 3],
+    /// Whether the shape is rotated along the effect's direction.
+    pub rotate: bool,
+}
+
+/// The behaviour of a type of effect: how it moves, reacts to the terrain, spawns
+/// sub-effects and is drawn.
+///
+/// Behaviours are shared by all the effects of a type and called from the parallel effect
+/// update, hence `Send + Sync`. Any randomness must come from the effect's own `rng`.
+///
+/// # Methods
+///
+/// - `name() -> &str`:
+///   Returns the name of the effect, shown on its button.
+/// - `lifetime() -> f32`:
+///   Returns the lifetime of the effect, in simulated seconds.
+/// - `advance(effect: &mut Effect, dt: f32)`:
+///   Moves the effect for `dt` simulated seconds.
+/// - `on_contact(effect: &mut Effect, material: &MaterialDef) -> Contact`:
+///   Decides what the effect does to a solid cell it touches.
+/// - `on_blocked(effect: &mut Effect)`:
+///   Reacts to a cell that survived the effect's damage, by default bouncing in a random direction.
+/// - `detonation(effect: &Effect) -> Option<Blast>`:
+///   Returns the blast to detonate without any contact (e.g. when a fuse burns out).
+/// - `spawn(effect: &mut Effect) -> Vec<Effect>`:
+///   Proposes sub-effects, called until the effect has spawned once.
+/// - `visual() -> EffectVisual`:
+///   Describes how the effect is drawn.
+pub trait EffectBehavior: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn lifetime(&self) -> f32 {
+        DEFAULT_LIFETIME
+    }
+
+    fn advance(&self, effect: &mut Effect, dt: f32);
+
+    fn on_contact(&self, effect: &mut Effect, material: &MaterialDef) -> Contact;
+
+    fn on_blocked(&self, effect: &mut Effect) {
+        effect.direction += std::f32::consts::PI
+            + effect.rng.random_range(-std::f32::consts::PI..std::f32::consts::PI);
+    }
+
+    fn detonation(&self, _effect: &Effect) -> Option<Blast> {
+        None
+    }
+
+    fn spawn(&self, _effect: &mut Effect) -> Vec<Effect> {
+        Vec::new()
+    }
+
+    fn visual(&self) -> EffectVisual;
+}
+
+// Layout of an effect file: the spawn cap and a list of `[[effect]]` tables
+#[derive(Deserialize)]
+struct EffectFile {
+    #[serde(default = "default_max_spawned_per_step")]
+    max_spawned_per_step: usize,
+    effect: Vec<EffectDef>,
+}
+
+fn default_max_spawned_per_step() -> usize {
+    10
+}
+
+/// The set of effect types known to the simulation, indexed by `EffectId`.
+///
+/// # Fields
+///
+/// - `behaviors`: The behaviour of each effect type.
+/// - `max_spawned_per_step`: The maximum number of sub-effects spawned during one simulation step.
+#[derive(Clone)]
+pub struct EffectRegistry {
+    behaviors: Vec<Arc<dyn EffectBehavior>>,
+    max_spawned_per_step: usize,
+}
+
+/// # Methods
+///
+/// - `new(max_spawned_per_step: usize) -> Self`:
+///   Creates an empty registry.
+/// - `from_file(path: &str, overrides: &Overrides) -> Result<Self, String>`:
+///   Loads a registry from a TOML effect file, with fields of its effects replaced.
+/// - `builtin(overrides: &Overrides) -> Result<Self, String>`:
+///   Builds the registry from the built-in effect definitions, with fields of its effects replaced.
+/// - `parse(source: &str) -> Result<Self, String>`:
+///   Parses a registry from the contents of a TOML effect file.
+/// - `register(behavior: impl EffectBehavior + 'static) -> EffectId`:
+///   Adds an effect type and returns its identifier.
+/// - `get(id: EffectId) -> &dyn EffectBehavior`:
+///   Returns the behaviour of an effect type.
+/// - `id(name: &str) -> Option<EffectId>`:
+///   Looks up an effect type by name.
+/// - `ids() -> impl Iterator<Item = EffectId>`:
+///   Iterates over the identifiers of every effect type, in registration order.
+/// - `len() -> usize`:
+///   Returns the number of effect types.
+/// - `max_spawned_per_step() -> usize`:
+///   Returns the maximum number of sub-effects spawned during one simulation step.
+impl EffectRegistry {
+    pub fn new(max_spawned_per_step: usize) -> Self {
+        Self { behaviors: Vec::new(), max_spawned_per_step }
+    }
+
+    pub fn from_file(path: &str, overrides: &Overrides) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        apply_overrides(&source, "effect", overrides).and_then(|source| Self::parse(&source)).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn builtin(overrides: &Overrides) -> Result<Self, String> {
+        apply_overrides(DEFAULT_EFFECTS, "effect", overrides).and_then(|source| Self::parse(&source))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let file: EffectFile = toml::from_str(source).map_err(|e| e.to_string())?;
+
+        // At least one effect must be selectable
+        if file.effect.is_empty() {
+            return Err("no effect defined".to_string());
+        }
+
+        // Identifiers are stored on a single byte
+        if file.effect.len() > u8::MAX as usize + 1 {
+            return Err(format!("too many effects ({}, at most 256)", file.effect.len()));
+        }
+
+        for (i, effect) in file.effect.iter().enumerate() {
+            // Names must be unique to be looked up
+            if file.effect[..i].iter().any(|e| e.name == effect.name) {
+                return Err(format!("duplicate effect \"{}\"", effect.name));
+            }
+
+            // Probabilities must be valid
+            if !(0.0..=1.0).contains(&effect.split_probability) {
+                return Err(format!("effect \"{}\": split_probability must be between 0 and 1", effect.name));
+            }
+
+            // Quantities must be finite and non-negative
+            let mut quantities = vec![
+                ("speed", effect.speed),
+                ("damage", effect.damage),
+                ("lifetime", effect.lifetime),
+                ("split_spread", effect.split_spread),
+                ("size", effect.size[0]),
+                ("size", effect.size[1]),
+            ];
+            quantities.extend(effect.fuse.map(|fuse| ("fuse", fuse)));
+            quantities.extend(effect.blast.map(|blast| ("blast damage", blast.damage)));
+            if let Some((field, _)) = quantities.iter().find(|(_, value)| !(value.is_finite() && *value >= 0.0)) {
+                return Err(format!("effect \"{}\": {} must be a finite, non-negative number", effect.name, field));
+            }
+
+            // The falloff of the blast divides the distance by its radius
+            if let Some(blast) = effect.blast
+                && !(blast.radius.is_finite() && blast.radius > 0.0)
+            {
+                return Err(format!("effect \"{}\": blast radius must be a finite, positive number", effect.name));
+            }
+
+            // A fuse detonates the blast, before the effect expires
+            if let Some(fuse) = effect.fuse {
+                if effect.blast.is_none() {
+                    return Err(format!("effect \"{}\": a fuse requires a blast", effect.name));
+                }
+                if fuse > effect.lifetime {
+                    return Err(format!("effect \"{}\": fuse must not exceed the lifetime", effect.name));
+                }
+            }
+        }
+
+        let mut registry = Self::new(file.max_spawned_per_step);
+        for effect in file.effect {
+            registry.register(effect);
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, behavior: impl EffectBehavior + 'static) -> EffectId {
+        assert!(self.behaviors.len() <= u8::MAX as usize, "Too many effect types");
+        self.behaviors.push(Arc::new(behavior));
+        EffectId((self.behaviors.len() - 1) as u8)
+    }
+
+    pub fn get(&self, id: EffectId) -> &dyn EffectBehavior {
+        self.behaviors[id.0 as usize].as_ref()
+    }
+
+    pub fn id(&self, name: &str) -> Option<EffectId> {
+        self.behaviors.iter().position(|b| b.name() == name).map(|i| EffectId(i as u8))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = EffectId> + use<> {
+        (0..self.behaviors.len()).map(|i| EffectId(i as u8))
+    }
+
+    pub fn len(&self) -> usize {
+        self.behaviors.len()
+    }
+
+    pub fn max_spawned_per_step(&self) -> usize {
+        self.max_spawned_per_step
+    }
+}
+
+/// Builds the registry from the built-in effect definitions.
+impl Default for EffectRegistry {
+    fn default() -> Self {
+        Self::parse(DEFAULT_EFFECTS).expect("Invalid built-in effect definitions")
+    }
+}
+
+/// Represents how the damage of a blast decreases with the distance to its centre.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Falloff {
+    /// The full damage is applied over the whole radius.
+    Constant,
+    /// The damage decreases linearly down to zero at the edge of the radius.
+    Linear,
+    /// The damage decreases quadratically, concentrating it near the centre.
+    Quadratic,
+}
+
+impl Falloff {
+    /// Returns the damage factor at a distance `t` from the centre, `t` ranging from 0 (centre) to 1 (edge).
+    pub fn factor(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Quadratic => (1.0 - t) * (1.0 - t),
+        }
+    }
+}
+
+/// Represents the damage dealt by an explosion around its centre.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Blast {
+    /// The radius of the blast, in cells.
+    pub radius: f32,
+    /// The damage dealt at the centre of the blast.
+    pub damage: f32,
+    /// How the damage decreases towards the edge of the blast.
+    pub falloff: Falloff,
+}
+
+impl Default for Blast {
+    fn default() -> Self {
+        Self { radius: 6.0, damage: 20.0, falloff: Falloff::Quadratic }
+    }
 }
 
 /// Represents a visual effect in the game, including its type, position, direction, 
 /// and other properties related to its behaviour.
 #[derive(Debug)]
 pub struct Effect {
-    /// The type of the effect (e.g., bubbles, lightning).
-    pub effect_type: EffectType,
+    /// The type of the effect (e.g., bubbles, lightning), indexing into the `EffectRegistry`.
+    pub effect_type: EffectId,
     /// The position of the effect in the game world, represented as (x, y) coordinates.
     pub position: (f32, f32),
     /// The direction of the effect's movement, represented as an angle in radians.
     pub direction: f32,
-    /// The timestamp indicating when the effect was started.
-    pub started_at: Instant,
+    /// The age of the effect, in simulated seconds.
+    pub age: f32,
     /// A flag indicating whether the effect has been spawned.
     pub spawned: bool,
+    /// The effect's own random stream, derived from the simulation's seed,
+    /// so that effects can be updated in parallel reproducibly.
+    pub rng: StdRng,
 }
 
 impl Effect {
+    /// Creates a sub-effect of the same type at the effect's position, moving in the given direction.
+    ///
+    /// The sub-effect draws its random stream from the effect's one, and never spawns itself.
+    pub fn child(&mut self, direction: f32) -> Effect {
+        Effect {
+            effect_type: self.effect_type,
+            position: self.position,
+            direction,
+            age: 0.0,
+            spawned: true,
+            rng: StdRng::seed_from_u64(self.rng.random()),
+        }
+    }
+
+    /// Moves the effect in a straight line along its direction.
+    ///
+    /// # Parameters
+    /// - `speed`: The speed of the effect, in world units per simulated second.
+    /// - `dt`: The elapsed simulated time, in seconds.
+    pub fn move_forward(&mut self, speed: f32, dt: f32) {
+        self.position.0 += speed * dt * self.direction.cos();
+        self.position.1 += speed * dt * self.direction.sin();
+    }
+
     /// Adjusts the direction of the effect when it collides with the boundaries of the terrain.
     ///
     /// # Parameters
@@ -54,4 +376,57 @@ impl Effect {
             self.direction = -self.direction;
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An effect file with a single effect, with extra fields
+    fn effect(fields: &str) -> String {
+        format!("[[effect]]\nname = \"Test\"\nspeed = 10.0\nshape = \"circle\"\nsize = [1.0, 1.0]\ncolor = [0, 0, 0]\n{}\n", fields)
+    }
+
+    // Parse an effect file expected to be rejected, returning the error
+    fn error(source: &str) -> String {
+        EffectRegistry::parse(source).err().expect("the effect file should be rejected")
+    }
+
+    #[test]
+    fn builtin_effects_parse() {
+        let effects = EffectRegistry::default();
+        assert!(effects.len() > 0);
+        assert!(effects.id("Explosion").is_some());
+        assert!(EffectRegistry::parse(&effect("fuse = 1.0\nblast = { radius = 2.0, damage = 5.0 }")).is_ok());
+    }
+
+    #[test]
+    fn rejects_empty_files() {
+        assert_eq!(error("max_spawned_per_step = 3\neffect = []\n"), "no effect defined");
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        assert_eq!(error(&format!("{}{}", effect(""), effect(""))), "duplicate effect \"Test\"");
+    }
+
+    #[test]
+    fn rejects_invalid_quantities() {
+        assert!(error(&effect("split_probability = 1.5")).contains("split_probability"));
+        assert!(error(&effect("damage = -1.0")).contains("damage must be a finite, non-negative number"));
+        assert!(error(&effect("lifetime = nan")).contains("lifetime"));
+        assert!(error(&effect("size = [1.0, inf]")).contains("size"));
+    }
+
+    #[test]
+    fn rejects_invalid_blasts() {
+        assert!(error(&effect("blast = { radius = 0.0, damage = 5.0 }")).contains("blast radius"));
+        assert!(error(&effect("blast = { radius = 2.0, damage = -5.0 }")).contains("blast damage"));
+    }
+
+    #[test]
+    fn rejects_invalid_fuses() {
+        assert!(error(&effect("fuse = 1.0")).contains("a fuse requires a blast"));
+        assert!(error(&effect("lifetime = 1.0\nfuse = 2.0\nblast = { radius = 2.0, damage = 5.0 }")).contains("fuse must not exceed the lifetime"));
+        assert!(error(&effect("fuse = -1.0\nblast = { radius = 2.0, damage = 5.0 }")).contains("fuse must be a finite"));
+    }
+}
@@ -0,0 +1,93 @@
+// -----------------------------------------------------------------------------
+// File: fallingsand.rs
+// Description: Cellular-automaton pass making granular materials fall and slide.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::chunks::ChunkedTerrain;
+use crate::materials::{MaterialId, MaterialRegistry};
+
+/// Runs one pass of falling sand over the terrain.
+///
+/// Every cell made of a granular material falls into the air cell below it, or sinks
+/// through the liquid cell below it.
+/// If the cell below is occupied, it slides diagonally down to the left or to the right
+/// (in a random order) when both the side and the diagonal cells are air.
+/// Other materials never move.
+///
+/// The cells of the modified chunks, and of the chunks just generated with loose cells, are swept from the bottom
+/// row up so that a grain moves at most once per pass. Grains only fall within the chunks next to these, which
+/// must be loaded, so that the pass does not depend on the chunks the view keeps loaded.
+///
+/// # Parameters
+/// - `terrain`: The chunked terrain, with `y` growing downwards.
+/// - `materials`: The material registry, telling which materials are granular.
+/// - `rng`: The random stream used to pick the sliding direction.
+///
+/// # Returns
+/// The coordinates of every cell that changed during the pass.
+pub fn update_falling_sand(terrain: &mut ChunkedTerrain, materials: &MaterialRegistry, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    let mut changed = Vec::new();
+    let height = terrain.height();
+
+    // The chunks the pass works on, before the grains make other chunks modified
+    let region = terrain.active_region();
+
+    // Cells out of reach are treated like cells outside the world
+    let material = |terrain: &ChunkedTerrain, x: usize, y: usize| {
+        region.contains(x, y).then(|| terrain.get(x, y).map(|cell| cell.material)).flatten()
+    };
+
+    // Sweep from the row just above the bottom up to the top
+    for y in (0..height.saturating_sub(1)).rev() {
+        for x in region.spans(y).into_iter().flatten() {
+            if !material(terrain, x, y).is_some_and(|material| materials.get(material).granular) {
+                continue;
+            }
+
+            // Fall straight down, swapping places with a liquid
+            let Some(below) = material(terrain, x, y + 1) else {
+                continue;
+            };
+            if below == MaterialId::AIR || materials.get(below).liquid {
+                move_cell(terrain, (x, y), (x, y + 1), &mut changed);
+                continue;
+            }
+
+            // Slide diagonally, trying both sides in a random order
+            let sides: [isize; 2] = if rng.random_bool(0.5) { [-1, 1] } else { [1, -1] };
+            for side in sides {
+                let nx = x as isize + side;
+                if nx < 0 {
+                    continue;
+                }
+                let nx = nx as usize;
+                if material(terrain, nx, y) == Some(MaterialId::AIR) && material(terrain, nx, y + 1) == Some(MaterialId::AIR) {
+                    move_cell(terrain, (x, y), (nx, y + 1), &mut changed);
+                    break;
+                }
+            }
+        }
+    }
+
+    changed
+}
+
+// Swap a cell with an air or liquid cell
+fn move_cell(terrain: &mut ChunkedTerrain, from: (usize, usize), to: (usize, usize), changed: &mut Vec<(usize, usize)>) {
+    let Some(displaced) = terrain.get(to.0, to.1).cloned() else {
+        return;
+    };
+    if let Some(cell) = terrain.set(from.0, from.1, displaced) {
+        terrain.set(to.0, to.1, cell);
+        changed.push(from);
+        changed.push(to);
+    }
+}
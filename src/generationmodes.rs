@@ -0,0 +1,42 @@
+// -----------------------------------------------------------------------------
+// File: generationmodes.rs
+// Description: The layouts the terrain generation can produce.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use std::fmt;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Represents how the generated terrain is laid out.
+///
+/// # Variants
+///
+/// - `Field`: The noise is sampled over the whole grid and split into materials by the terrain layers,
+///   giving blobs of ground seen from above.
+/// - `Sidescroller`: A side view, with a surface line from the noise, ground below it and caves carved
+///   into the ground.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum GenerationMode {
+    #[default]
+    #[serde(alias = "field")]
+    Field,
+
+    #[serde(alias = "sidescroller")]
+    Sidescroller,
+}
+
+impl fmt::Display for GenerationMode {
+
+    /// Formats the `GenerationMode` enum as a string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerationMode::Field => write!(f, "field"),
+            GenerationMode::Sidescroller => write!(f, "sidescroller"),
+        }
+    }
+}
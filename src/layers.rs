@@ -0,0 +1,295 @@
+// -----------------------------------------------------------------------------
+// File: layers.rs
+// Description: Mapping of noise values to materials with ordered threshold
+//              bands, and depth rules layering the generated ground.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::fs;
+
+use crate::cell::Cell;
+use crate::materials::{MaterialId, MaterialRegistry};
+
+/// A band of noise values, as read from a layer file.
+///
+/// # Fields
+///
+/// - `below`: The noise value under which a cell falls in this band. Only the last band may omit it.
+/// - `material`: The name of the material of the cells in this band.
+/// - `durability`: The durability of the cells in this band, instead of the material's.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BandDef {
+    #[serde(default)]
+    pub below: Option<f64>,
+    pub material: String,
+    #[serde(default)]
+    pub durability: Option<f32>,
+}
+
+/// The surface rule, as read from a layer file.
+///
+/// # Fields
+///
+/// - `material`: The name of the material of the solid cells near the air above them.
+/// - `depth`: The thickness of the surface, in cells.
+/// - `beneath`: The name of the material the surface material turns into away from the surface, if any.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SurfaceDef {
+    pub material: String,
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+    #[serde(default)]
+    pub beneath: Option<String>,
+}
+
+/// The deep rule, as read from a layer file.
+///
+/// # Fields
+///
+/// - `material`: The name of the material of the solid cells far below the air.
+/// - `depth`: The depth from which solid cells take the material, in cells.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeepDef {
+    pub material: String,
+    pub depth: usize,
+}
+
+/// The bedrock rule, as read from a layer file.
+///
+/// # Fields
+///
+/// - `material`: The name of the material of the bottom rows, typically indestructible.
+/// - `rows`: The number of bottom rows.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BedrockDef {
+    pub material: String,
+    #[serde(default = "default_depth")]
+    pub rows: usize,
+}
+
+fn default_depth() -> usize {
+    1
+}
+
+// Layout of a layer file: a list of `[[band]]` tables and the optional depth rules
+#[derive(Deserialize)]
+struct LayerFile {
+    band: Vec<BandDef>,
+    #[serde(default)]
+    surface: Option<SurfaceDef>,
+    #[serde(default)]
+    deep: Option<DeepDef>,
+    #[serde(default)]
+    bedrock: Option<BedrockDef>,
+}
+
+// A band resolved against the material registry
+#[derive(Debug, Clone, Copy)]
+struct Band {
+    below: f64,
+    material: MaterialId,
+    durability: f32,
+}
+
+/// The layering of the generated terrain: the bands turning noise values into materials,
+/// then the depth rules applied to each column.
+///
+/// The depth of a solid cell is the number of solid cells between it and the closest air
+/// (or liquid) cell above it; cells with no air above them are infinitely deep.
+///
+/// # Fields
+///
+/// - `bands`: The bands, by increasing noise value.
+/// - `surface`: The material, thickness and material beneath of the surface, if any.
+/// - `deep`: The material and starting depth of the deep ground, if any.
+/// - `bedrock`: The material and number of the bottom rows, if any.
+#[derive(Debug, Clone)]
+pub struct TerrainLayers {
+    bands: Vec<Band>,
+    surface: Option<(MaterialId, usize, Option<MaterialId>)>,
+    deep: Option<(MaterialId, usize)>,
+    bedrock: Option<(MaterialId, usize)>,
+}
+
+/// # Methods
+///
+/// - `from_file(path: &str, materials: &MaterialRegistry) -> Result<Self, String>`:
+///   Loads the layers from a TOML layer file.
+/// - `parse(source: &str, materials: &MaterialRegistry) -> Result<Self, String>`:
+///   Parses the layers from the contents of a TOML layer file.
+/// - `builtin(materials: &MaterialRegistry) -> Result<Self, String>`:
+///   Returns the built-in bands (air, grass, rock), without depth rules. Fails if a material is missing from the registry.
+/// - `cell(value: f64) -> Cell`:
+///   Returns the cell of the band a noise value falls in.
+/// - `apply_depth_rules(terrain: &mut [Vec<Cell>], materials: &MaterialRegistry)`:
+///   Layers the columns of a terrain grid with the depth rules.
+impl TerrainLayers {
+    pub fn from_file(path: &str, materials: &MaterialRegistry) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::parse(&source, materials).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(source: &str, materials: &MaterialRegistry) -> Result<Self, String> {
+        let file: LayerFile = toml::from_str(source).map_err(|e| e.to_string())?;
+        let material = |name: &str| materials.id(name).ok_or(format!("unknown material \"{}\"", name));
+
+        if file.band.is_empty() {
+            return Err("at least one band is required".to_string());
+        }
+
+        // Bands must be ordered, and cover every noise value
+        let mut bands = Vec::with_capacity(file.band.len());
+        for (i, def) in file.band.iter().enumerate() {
+            let last = i == file.band.len() - 1;
+            let below = match def.below {
+                Some(below) if below.is_nan() => return Err(format!("band {} has an invalid threshold", i + 1)),
+                Some(below) => below,
+                None if last => f64::INFINITY,
+                None => return Err(format!("band {} needs a threshold (only the last band may omit it)", i + 1)),
+            };
+            if let Some(previous) = bands.last().map(|band: &Band| band.below)
+                && below <= previous
+            {
+                return Err(format!("band {} must have a higher threshold than the previous one", i + 1));
+            }
+
+            // Damage is compared with the durability
+            if def.durability.is_some_and(|durability| !(durability.is_finite() && durability >= 0.0)) {
+                return Err(format!("band {}: durability must be a finite, non-negative number", i + 1));
+            }
+
+            let id = material(&def.material)?;
+            let durability = def.durability.unwrap_or(materials.get(id).durability);
+            bands.push(Band { below, material: id, durability });
+        }
+
+        let surface = match &file.surface {
+            Some(def) => Some((material(&def.material)?, def.depth, def.beneath.as_deref().map(material).transpose()?)),
+            None => None,
+        };
+        let deep = match &file.deep {
+            Some(def) => Some((material(&def.material)?, def.depth)),
+            None => None,
+        };
+        let bedrock = match &file.bedrock {
+            Some(def) => Some((material(&def.material)?, def.rows)),
+            None => None,
+        };
+
+        Ok(Self { bands, surface, deep, bedrock })
+    }
+
+    pub fn builtin(materials: &MaterialRegistry) -> Result<Self, String> {
+        let band = |below: f64, name: &str| {
+            let material = materials.id(name).ok_or(format!("unknown material \"{}\"", name))?;
+            Ok::<_, String>(Band { below, material, durability: materials.get(material).durability })
+        };
+        Ok(Self {
+            bands: vec![band(-0.2, "air")?, band(0.2, "grass")?, band(f64::INFINITY, "rock")?],
+            surface: None,
+            deep: None,
+            bedrock: None,
+        })
+    }
+
+    pub fn cell(&self, value: f64) -> Cell {
+        // The last band takes every value above the others
+        let band = self.bands.iter().find(|band| value < band.below).or(self.bands.last()).unwrap();
+        Cell { material: band.material, durability: band.durability }
+    }
+
+    pub fn apply_depth_rules(&self, terrain: &mut [Vec<Cell>], materials: &MaterialRegistry) {
+        let solid = |material: MaterialId| material != MaterialId::AIR && !materials.get(material).liquid;
+        let set = |cell: &mut Cell, material: MaterialId| {
+            if cell.material != material {
+                *cell = Cell { material, durability: materials.get(material).durability };
+            }
+        };
+
+        // Columns are independent, so they are layered in parallel
+        terrain.par_iter_mut().for_each(|column| {
+            // Depth below the closest air above, none until air is met
+            let mut depth: Option<usize> = None;
+            for cell in column.iter_mut() {
+                if !solid(cell.material) {
+                    depth = Some(0);
+                    continue;
+                }
+
+                if let Some((surface, thickness, beneath)) = self.surface {
+                    if depth.is_some_and(|depth| depth < thickness) {
+                        set(cell, surface);
+                    } else if cell.material == surface
+                        && let Some(beneath) = beneath
+                    {
+                        set(cell, beneath);
+                    }
+                }
+                if let Some((deep, from)) = self.deep
+                    && depth.is_none_or(|depth| depth >= from)
+                {
+                    set(cell, deep);
+                }
+
+                depth = depth.map(|depth| depth + 1);
+            }
+
+            // Bottom rows, whatever they hold
+            if let Some((bedrock, rows)) = self.bedrock {
+                let start = column.len().saturating_sub(rows);
+                for cell in &mut column[start..] {
+                    set(cell, bedrock);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parse a layer file expected to be rejected with the built-in materials, returning the error
+    fn error(source: &str) -> String {
+        TerrainLayers::parse(source, &MaterialRegistry::default()).unwrap_err()
+    }
+
+    #[test]
+    fn bands_split_noise_values() {
+        let materials = MaterialRegistry::default();
+        let source = "[[band]]\nbelow = 0.0\nmaterial = \"air\"\n\n[[band]]\nmaterial = \"rock\"\ndurability = 7.5\n";
+        let layers = TerrainLayers::parse(source, &materials).unwrap();
+        assert_eq!(layers.cell(-0.5).material, MaterialId::AIR);
+        assert_eq!(layers.cell(0.5).material, materials.id("rock").unwrap());
+        assert_eq!(layers.cell(0.5).durability, 7.5);
+    }
+
+    #[test]
+    fn rejects_invalid_bands() {
+        assert!(error("band = []\n").contains("at least one band"));
+        assert!(error("[[band]]\nmaterial = \"air\"\n\n[[band]]\nmaterial = \"rock\"\n").contains("needs a threshold"));
+        assert!(error("[[band]]\nbelow = nan\nmaterial = \"air\"\n\n[[band]]\nmaterial = \"rock\"\n").contains("invalid threshold"));
+        assert!(error("[[band]]\nbelow = 0.5\nmaterial = \"air\"\n\n[[band]]\nbelow = 0.5\nmaterial = \"rock\"\n").contains("higher threshold"));
+        assert_eq!(error("[[band]]\nmaterial = \"marble\"\n"), "unknown material \"marble\"");
+    }
+
+    #[test]
+    fn rejects_invalid_durabilities() {
+        for durability in ["-1.0", "nan", "inf"] {
+            let source = format!("[[band]]\nmaterial = \"rock\"\ndurability = {}\n", durability);
+            assert_eq!(error(&source), "band 1: durability must be a finite, non-negative number");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_rule_materials() {
+        let source = "[[band]]\nmaterial = \"rock\"\n\n[surface]\nmaterial = \"grass\"\nbeneath = \"dirt\"\n";
+        assert_eq!(error(source), "unknown material \"dirt\"");
+    }
+}
@@ -0,0 +1,105 @@
+// -----------------------------------------------------------------------------
+// File: liquids.rs
+// Description: Cellular-automaton pass making liquid materials flow.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+
+use crate::cell::Cell;
+use crate::chunks::ChunkedTerrain;
+use crate::materials::{MaterialId, MaterialRegistry};
+
+/// Runs one pass of liquid flow over the terrain.
+///
+/// Every liquid cell first dissolves the neighbouring cells whose material it dissolves
+/// (e.g. lava burning grass), turning them into air. It then falls into the air cell below it,
+/// or slides diagonally down, or finally spreads sideways, which lets liquids fill cavities.
+/// A liquid only spreads sideways towards a drop it can reach along its row, so that a level liquid stays still.
+///
+/// The cells of the modified chunks, and of the chunks just generated with loose cells, are swept from the bottom
+/// row up, and a cell moves at most once per pass. Liquids only flow, dissolve and look for drops within the chunks
+/// next to these, which must be loaded, so that the pass does not depend on the chunks the view keeps loaded.
+///
+/// # Parameters
+/// - `terrain`: The chunked terrain, with `y` growing downwards.
+/// - `materials`: The material registry, telling which materials are liquid.
+/// - `rng`: The random stream used to pick the flowing direction.
+///
+/// # Returns
+/// The coordinates of every cell that changed during the pass.
+pub fn update_liquids(terrain: &mut ChunkedTerrain, materials: &MaterialRegistry, rng: &mut StdRng) -> Vec<(usize, usize)> {
+    let mut changed = Vec::new();
+    let height = terrain.height();
+
+    // Resolve the materials dissolved by each material once per pass
+    let dissolved: Vec<Vec<MaterialId>> = (0..materials.len())
+        .map(|i| materials.get(MaterialId(i as u8)).dissolves.iter().filter_map(|name| materials.id(name)).collect())
+        .collect();
+
+    // Cells that already moved during this pass
+    let mut moved = HashSet::new();
+
+    // The chunks the pass works on, before the liquids make other chunks modified
+    let region = terrain.active_region();
+
+    for y in (0..height).rev() {
+        for x in region.spans(y).into_iter().flatten() {
+            let material = terrain.get(x, y).map_or(MaterialId::AIR, |cell| cell.material);
+            if moved.contains(&(x, y)) || !materials.get(material).liquid {
+                continue;
+            }
+
+            // Dissolve the neighbouring cells on contact
+            let dissolves = &dissolved[material.0 as usize];
+            if !dissolves.is_empty() {
+                for (nx, ny) in [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)] {
+                    if region.contains(nx, ny) && terrain.get(nx, ny).is_some_and(|cell| dissolves.contains(&cell.material)) {
+                        terrain.set(nx, ny, Cell { material: MaterialId::AIR, durability: 0.0 });
+                        changed.push((nx, ny));
+                    }
+                }
+            }
+
+            // Cells outside the world or out of reach are never air
+            let is_air = |terrain: &ChunkedTerrain, x: isize, y: usize| {
+                x >= 0 && region.contains(x as usize, y) && terrain.get(x as usize, y).is_some_and(|cell| cell.material == MaterialId::AIR)
+            };
+            let sides: [isize; 2] = if rng.random_bool(0.5) { [-1, 1] } else { [1, -1] };
+            let ix = x as isize;
+
+            // Fall straight down, then diagonally down, then sideways
+            let target = if is_air(terrain, ix, y + 1) {
+                Some((x, y + 1))
+            } else if let Some(side) = sides.iter().find(|&&side| is_air(terrain, ix + side, y) && is_air(terrain, ix + side, y + 1)) {
+                Some(((ix + side) as usize, y + 1))
+            } else {
+                // Spread towards the nearest air cell with air below it, over air cells
+                let reaches_drop = |side: isize| {
+                    (1..)
+                        .map(|step| ix + side * step)
+                        .take_while(|&nx| is_air(terrain, nx, y))
+                        .any(|nx| is_air(terrain, nx, y + 1))
+                };
+                sides.iter().find(|&&side| reaches_drop(side)).map(|side| ((ix + side) as usize, y))
+            };
+
+            if let Some((tx, ty)) = target
+                && let Some(cell) = terrain.set(x, y, Cell { material: MaterialId::AIR, durability: 0.0 })
+            {
+                terrain.set(tx, ty, cell);
+                moved.insert((tx, ty));
+                changed.push((x, y));
+                changed.push((tx, ty));
+            }
+        }
+    }
+
+    changed
+}
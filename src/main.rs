@@ -5,8 +5,8 @@
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: March 13, 2025
-// Last modified: March 26, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
 #![allow(unused)]
@@ -23,31 +23,75 @@ use std::f32::consts::TAU;
 use std::time::{Duration, Instant};
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use rand::Rng;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 mod quadtree;
 use crate::quadtree::{QuadTree, QuadTreeItem};
 
 mod materials;
-use crate::materials::Material;
+use crate::materials::MaterialRegistry;
 
 mod cell;
 use crate::cell::Cell;
 
 mod effect;
-use crate::effect::{Effect, EffectType};
+use crate::effect::{Effect, EffectRegistry};
+
+mod behaviors;
 
 mod mainstate;
-use crate::mainstate::MainState;
+use crate::mainstate::{MainState, SessionOptions};
 
 mod noisetypes;
 use crate::noisetypes::NoiseType;
 
+mod generationmodes;
+use crate::generationmodes::GenerationMode;
+
+mod sidescroller;
+use crate::sidescroller::ground_materials;
+
 mod noisegenerator;
-use crate::noisegenerator::NoiseGenerator;
+use crate::noisegenerator::{DEFAULT_FREQUENCY, MAX_OCTAVES, NoiseGenerator, NoiseParams};
+
+mod noisegraph;
+use crate::noisegraph::NoiseGraph;
+
+mod simulation;
+use crate::simulation::Simulation;
+
+mod fallingsand;
+
+mod collapse;
+
+mod chunks;
+
+mod camera;
+
+mod config;
+use crate::config::{Config, ConfigFile};
+
+mod liquids;
+
+mod layers;
+use crate::layers::TerrainLayers;
+
+mod replay;
+use crate::replay::Replay;
+
+mod save;
+use crate::save::TerrainSnapshot;
+
+mod terrainimage;
+use crate::terrainimage::{ImageMode, export_terrain_image, import_terrain_image};
+
+// Default definition files
+const DEFAULT_MATERIALS: &str = "resources/materials.toml";
+const DEFAULT_EFFECTS: &str = "resources/effects.toml";
+const DEFAULT_LAYERS: &str = "resources/layers.toml";
 
 // Minimum value for width
 const MIN_WIDTH: u32 = 500;
@@ -66,118 +110,248 @@ const UI_HEIGHT: f32 = 50.0;
 
 /// A struct representing the command-line arguments for configuring the application.
 ///
+/// The settings may also be given by a configuration file, the flags overriding it.
+///
 /// # Fields
 ///
+/// * `config` - A TOML configuration file giving the settings of the flags below, up to `layers`, and overrides of single
+///   materials and effects.
 /// * `width` - The width of the window. Must be at least 500. Defaults to 700.
 /// * `height` - The height of the window. Must be at least 300. Defaults to 500.
 /// * `delta` - The fixed delta time for the simulation in milliseconds. Must be at least 15. Defaults to 15.
 /// * `cellsize` - The fixed size of each cell in the simulation. Must be at least 5.0. Defaults to 5.0.
+/// * `world_width` - The width of the world, in cells. Must be at least 1. Defaults to the width of the window.
+/// * `world_height` - The height of the world, in cells. Must be at least 1. Defaults to the height of the window.
+/// * `mode` - How the generated terrain is laid out (`field` or `sidescroller`). Defaults to `field`.
+/// * `frequency` - The frequency of the noise, per cell, applied to every noise. Must be positive. Defaults to 0.05.
+/// * `octaves` - The number of octaves of the fractal noises. Must be between 1 and 32. Defaults to the noise's own.
+/// * `lacunarity` - The frequency multiplier between octaves. Must be at least 1. Defaults to the noise's own.
+/// * `persistence` - The amplitude multiplier between octaves. Must be in `(0, 1]`. Defaults to the noise's own.
+///   The octaves, lacunarity and persistence only apply to the fractal noises (fbm, ridged-multi, billow), and are
+///   ignored by the others, e.g. worley.
+/// * `noise_graph` - A TOML file defining a graph of noise modules, used instead of the single noise.
+/// * `falling_sand` / `no_falling_sand` - Whether granular materials fall and slide, the last one given winning. Defaults to false.
+/// * `collapse` / `no_collapse` - Whether unsupported chunks of terrain collapse, the last one given winning. Defaults to false.
+/// * `materials` - The TOML file defining the terrain materials. Defaults to `resources/materials.toml`.
+/// * `effects` - The TOML file defining the effects. Defaults to `resources/effects.toml`.
+/// * `layers` - The TOML file defining the terrain layers. Defaults to `resources/layers.toml`.
+/// * `terrain_image` - An image (PNG) the terrain is imported from, instead of being generated.
+/// * `image_mode` - How the pixels of the terrain image are turned into materials. Defaults to `palette`.
+/// * `load` - A saved terrain to start from. Its world dimensions, cell size, seed, generation mode, noise type and noise parameters
+///   override the other flags.
+/// * `save_on_exit` - A file the terrain is saved to when quitting.
+/// * `record` - A file the session (every action of the user, with its tick) is recorded to when quitting.
+/// * `replay` - A recorded session to replay. Its world dimensions, cell size, delta time, seed, generation mode, noise type,
+///   noise parameters, falling sand and collapse settings override the other flags.
+/// * `command` - An optional command run without opening a window (e.g. `export`, `print-config`).
 #[derive(Parser)]
 #[command(name = "Terrain Destruction")]
 #[command(about = "A terrain destruction simulation", long_about = None)]
 struct Args {
-    /// Width of the window (minimum 500)
-    #[arg(long, default_value_t = 700)]
-    width: u32,
+    /// Configuration file (TOML) giving the settings, overridden by the flags
+    #[arg(long)]
+    config: Option<String>,
 
-    /// Height of the window (minimum 300)
-    #[arg(long, default_value_t = 500)]
-    height: u32,
+    /// Width of the window (minimum 500, default 700)
+    #[arg(long)]
+    width: Option<u32>,
 
-    /// Fixed delta time for the simulation (minimum 15)
-    #[arg(long, default_value_t = 15)]
-    delta: u32,
+    /// Height of the window (minimum 300, default 500)
+    #[arg(long)]
+    height: Option<u32>,
 
-    /// Fixed cell size for the simulation (minimum 5.0)
-    #[arg(long, default_value_t = 5.0)]
-    cellsize: f32,
+    /// Fixed delta time for the simulation (minimum 15, default 15)
+    #[arg(long)]
+    delta: Option<u32>,
 
-    /// Seed for noise
-    #[arg(long, default_value_t = -1)]
-    seed: i64,
+    /// Fixed cell size for the simulation (minimum 5.0, default 5.0)
+    #[arg(long)]
+    cellsize: Option<f32>,
 
-    /// Noise type for terrain generation
-    #[arg(long, default_value_t = NoiseType::Perlin)]
-    noise: NoiseType,
-}
+    /// Width of the world in cells (defaults to the width of the window)
+    #[arg(long)]
+    world_width: Option<usize>,
 
-// Constants
-lazy_static::lazy_static! {
-    static ref SCREEN_WIDTH: RwLock<f32> = RwLock::new(700.0);
-    static ref SCREEN_HEIGHT: RwLock<f32> = RwLock::new(500.0);
-    static ref FIXED_DELTA: RwLock<f32> = RwLock::new(1.0 / 15.0);
-    static ref TERRAIN_WIDTH : RwLock<usize> = RwLock::new(140);
-    static ref TERRAIN_HEIGHT : RwLock<usize> = RwLock::new(100);
-    static ref CELL_SIZE: RwLock<f32> = RwLock::new(5.0);
-    static ref SEED: RwLock<i64> = RwLock::new(-1);
-    static ref NOISETYPE: RwLock<NoiseType> = RwLock::new(NoiseType::Perlin);
-}
+    /// Height of the world in cells (defaults to the height of the window)
+    #[arg(long)]
+    world_height: Option<usize>,
 
-// Function to read constants of screen size width
-pub fn read_screen_width() -> f32 {
-    *SCREEN_WIDTH.read().unwrap()
-}
+    /// Seed for noise (default -1, a random seed)
+    #[arg(long)]
+    seed: Option<i64>,
 
-// Function to read constants of screen size height
-pub fn read_screen_height() -> f32 {
-    *SCREEN_HEIGHT.read().unwrap()
-}
+    /// Noise type for terrain generation (default perlin)
+    #[arg(long)]
+    noise: Option<NoiseType>,
 
-// Function to read constants of terrain size width
-pub fn read_terrain_width() -> usize {
-    *TERRAIN_WIDTH.read().unwrap()
-}
+    /// Layout of the generated terrain: noise blobs (field, the default) or a side view with caves (sidescroller)
+    #[arg(long)]
+    mode: Option<GenerationMode>,
 
-// Function to read constants of terrain size height
-pub fn read_terrain_height() -> usize {
-    *TERRAIN_HEIGHT.read().unwrap()
-}
+    /// Frequency of every noise per cell; lower values give larger features (default 0.05)
+    #[arg(long, alias = "scale")]
+    frequency: Option<f64>,
+
+    /// Number of octaves of the fractal noises (fbm, ridged-multi, billow), 1 to 32; the others, e.g. worley, ignore it
+    #[arg(long)]
+    octaves: Option<usize>,
+
+    /// Frequency multiplier between the octaves of the fractal noises (at least 1)
+    #[arg(long)]
+    lacunarity: Option<f64>,
+
+    /// Amplitude multiplier between the octaves of the fractal noises (above 0, at most 1)
+    #[arg(long)]
+    persistence: Option<f64>,
+
+    /// Noise graph definitions file (TOML), combining noise modules instead of a single noise
+    #[arg(long)]
+    noise_graph: Option<String>,
+
+    /// Let granular materials (e.g. sand) fall and slide
+    #[arg(long, overrides_with = "no_falling_sand")]
+    falling_sand: bool,
+
+    /// Keep granular materials in place, even if the configuration file lets them fall
+    #[arg(long, overrides_with = "falling_sand")]
+    no_falling_sand: bool,
+
+    /// Make unsupported chunks of terrain collapse
+    #[arg(long, overrides_with = "no_collapse")]
+    collapse: bool,
+
+    /// Keep unsupported chunks of terrain in place, even if the configuration file makes them collapse
+    #[arg(long, overrides_with = "collapse")]
+    no_collapse: bool,
+
+    /// Material definitions file (TOML, default resources/materials.toml)
+    #[arg(long)]
+    materials: Option<String>,
+
+    /// Effect definitions file (TOML, default resources/effects.toml)
+    #[arg(long)]
+    effects: Option<String>,
+
+    /// Terrain layer definitions file (TOML, default resources/layers.toml)
+    #[arg(long)]
+    layers: Option<String>,
+
+    /// Image (PNG) to import the terrain from
+    #[arg(long)]
+    terrain_image: Option<String>,
+
+    /// How the pixels of the terrain image are turned into materials
+    #[arg(long, default_value_t = ImageMode::Palette)]
+    image_mode: ImageMode,
+
+    /// Saved terrain to load at startup (binary, or RON for a .ron file)
+    #[arg(long)]
+    load: Option<String>,
+
+    /// File to save the terrain to when quitting (binary, or RON for a .ron file)
+    #[arg(long)]
+    save_on_exit: Option<String>,
+
+    /// File to record the session to when quitting (RON)
+    #[arg(long)]
+    record: Option<String>,
 
-// Function to read constants of fixed delta time
-pub fn read_delta() -> f32 {
-    *FIXED_DELTA.read().unwrap()
+    /// Recorded session to replay (RON)
+    #[arg(long)]
+    replay: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-// Function to read constants of cell size
-pub fn read_cell_size() -> f32 {
-    *CELL_SIZE.read().unwrap()
+/// The commands run without opening a window.
+///
+/// # Variants
+///
+/// * `Export` - Builds the terrain from the other flags and exports it to a PNG image.
+/// * `PrintConfig` - Prints the effective configuration, from the configuration file and the other flags, or saves it to a file.
+#[derive(Subcommand)]
+enum Command {
+    /// Build the terrain and export it to a PNG image, without opening a window
+    Export {
+        /// PNG file to write
+        output: String,
+
+        /// Draw each cell as a block of `cellsize` pixels instead of a single pixel
+        #[arg(long, default_value_t = false)]
+        full_scale: bool,
+    },
+
+    /// Print the effective configuration (TOML), without opening a window
+    PrintConfig {
+        /// TOML file to save the configuration to, instead of printing it
+        output: Option<String>,
+    },
 }
 
-// Function to read constants of seed
-pub fn read_seed() -> i64 {
-    *SEED.read().unwrap()
+impl Args {
+    // The settings given on the command line, overriding the configuration file
+    fn overrides(&self) -> ConfigFile {
+        ConfigFile {
+            width: self.width,
+            height: self.height,
+            delta: self.delta,
+            cellsize: self.cellsize,
+            world_width: self.world_width,
+            world_height: self.world_height,
+            seed: self.seed,
+            noise: self.noise,
+            mode: self.mode,
+            frequency: self.frequency,
+            octaves: self.octaves,
+            lacunarity: self.lacunarity,
+            persistence: self.persistence,
+            noise_graph: self.noise_graph.clone(),
+            falling_sand: switch(self.falling_sand, self.no_falling_sand),
+            collapse: switch(self.collapse, self.no_collapse),
+            materials: self.materials.clone(),
+            effects: self.effects.clone(),
+            layers: self.layers.clone(),
+            // Definitions are only overridden from a configuration file
+            material_overrides: None,
+            effect_overrides: None,
+        }
+    }
 }
 
-// Function to read constants of noise type
-pub fn read_noisetype() -> NoiseType {
-    *NOISETYPE.read().unwrap()
+// The setting of a switch given on the command line, if either of its flags was
+fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
 }
 
-// Update constants
-fn update_constants(width: u32, height: u32, delta: u32, cell_size: f32, seed: i64, noise: NoiseType) {
+// Build the configuration from the validated settings
+#[allow(clippy::too_many_arguments)]
+fn build_config(width: u32, height: u32, world_width: Option<usize>, world_height: Option<usize>, delta: u32, cell_size: f32, seed: i64, mode: GenerationMode, noise: NoiseType, noise_params: NoiseParams, falling_sand: bool, collapse: bool) -> Config {
     let cell_size = cell_size.max(MIN_SIZE_CELL);
 
     // Adjust width and height to be multiples of cell_size
     let adjusted_width = (width as f32 / cell_size).floor() * cell_size;
     let adjusted_height = (height as f32 / cell_size).floor() * cell_size;
 
-    let delta = 1.0 / delta.max(MIN_DELTA) as f32;
-
-    *SCREEN_WIDTH.write().unwrap() = adjusted_width;
-    *SCREEN_HEIGHT.write().unwrap() = adjusted_height;
-    *FIXED_DELTA.write().unwrap() = delta;
-
-    // Update terrain dimensions based on adjusted screen size
-    let terrain_width = (adjusted_width / cell_size) as usize;
-    let terrain_height = (adjusted_height / cell_size) as usize;
-    *TERRAIN_WIDTH.write().unwrap() = terrain_width;
-    *TERRAIN_HEIGHT.write().unwrap() = terrain_height;
-
-    *CELL_SIZE.write().unwrap() = cell_size;
-
-    *SEED.write().unwrap() = seed;
-
-    *NOISETYPE.write().unwrap() = noise;
+    Config {
+        screen_width: adjusted_width,
+        screen_height: adjusted_height,
+        delta: 1.0 / delta.max(MIN_DELTA) as f32,
+        // Terrain dimensions fit the adjusted screen size unless given
+        terrain_width: world_width.unwrap_or((adjusted_width / cell_size) as usize),
+        terrain_height: world_height.unwrap_or((adjusted_height / cell_size) as usize),
+        cell_size,
+        seed,
+        mode,
+        noise_type: noise,
+        noise_params,
+        falling_sand,
+        collapse,
+    }
 }
 
 pub fn main() -> GameResult {
@@ -185,60 +359,313 @@ pub fn main() -> GameResult {
     // Parse command line arguments
     let args = Args::parse();
 
-    // Initialize variables
+    // Read the configuration file, the flags given overriding its settings
+    let file = match args.config.as_deref().map(ConfigFile::from_file) {
+        Some(Ok(file)) => file,
+        Some(Err(e)) => {
+            println!("Warning: Could not load the configuration ({}). Using the command-line flags instead.", e);
+            ConfigFile::default()
+        }
+        None => ConfigFile::default(),
+    };
+    let settings = file.merge(args.overrides());
+
+    // Initialize variables, unset settings taking their default value
+    let defaults = Config::default();
+    let width = settings.width.unwrap_or(defaults.screen_width as u32);
+    let height = settings.height.unwrap_or(defaults.screen_height as u32);
+    let delta = settings.delta.unwrap_or((1.0 / defaults.delta).round() as u32);
+    let cell_size = settings.cellsize.unwrap_or(defaults.cell_size);
+    let frequency = settings.frequency.unwrap_or(defaults.noise_params.frequency);
+    let materials_path = settings.materials.clone().unwrap_or(DEFAULT_MATERIALS.to_string());
+    let effects_path = settings.effects.clone().unwrap_or(DEFAULT_EFFECTS.to_string());
+    let layers_path = settings.layers.clone().unwrap_or(DEFAULT_LAYERS.to_string());
+
     // Check if any arguments are below the minimum values and display a message
-    let width = if args.width < MIN_WIDTH {
+    let width = if width < MIN_WIDTH {
         println!("Warning: Width is below the minimum value of {}. Using {} instead.", MIN_WIDTH, MIN_WIDTH);
         MIN_WIDTH
     } else {
-        args.width.max(MIN_WIDTH)
+        width
     };
 
-    let height= if args.height < MIN_HEIGHT {
+    let height = if height < MIN_HEIGHT {
         println!("Warning: Height is below the minimum value of {}. Using {} instead.", MIN_HEIGHT, MIN_HEIGHT);
         MIN_HEIGHT
     } else {
-        args.height.max(MIN_HEIGHT)
+        height
     };
 
-    let delta = if args.delta < MIN_DELTA {
+    let delta = if delta < MIN_DELTA {
         println!("Warning: Delta time is below the minimum value of {}. Using {} instead.", MIN_DELTA, MIN_DELTA);
         MIN_DELTA
     } else {
-        args.delta.max(MIN_DELTA)
+        delta
     };
 
-    let cell_size = if args.cellsize < MIN_SIZE_CELL {
+    let cell_size = if cell_size < MIN_SIZE_CELL {
         println!("Warning: Cell size is below the minimum value of {}. Using {} instead.", MIN_SIZE_CELL, MIN_SIZE_CELL);
         MIN_SIZE_CELL
     } else {
-        args.cellsize.max(MIN_SIZE_CELL)
+        cell_size
     };
     
-    let noise = if args.noise == NoiseType::Perlin || args.noise == NoiseType::Fbm || args.noise == NoiseType::Simplex {
-        args.noise
+    let world_width = match settings.world_width {
+        Some(0) => {
+            println!("Warning: World width must be at least 1. Using the width of the window instead.");
+            None
+        }
+        world_width => world_width,
+    };
+
+    let world_height = match settings.world_height {
+        Some(0) => {
+            println!("Warning: World height must be at least 1. Using the height of the window instead.");
+            None
+        }
+        world_height => world_height,
+    };
+
+    let noise = settings.noise.unwrap_or(defaults.noise_type);
+
+    let frequency = if frequency > 0.0 && frequency.is_finite() {
+        frequency
     } else {
-        println!("Warning: No provided noise type is not supported. Using Perlin noise instead.");
-        NoiseType::Perlin
+        println!("Warning: Frequency must be positive. Using {} instead.", DEFAULT_FREQUENCY);
+        DEFAULT_FREQUENCY
+    };
+
+    let octaves = match settings.octaves {
+        Some(octaves) if !(1..=MAX_OCTAVES).contains(&octaves) => {
+            let clamped = octaves.clamp(1, MAX_OCTAVES);
+            println!("Warning: Octaves must be between 1 and {}. Using {} instead.", MAX_OCTAVES, clamped);
+            Some(clamped)
+        }
+        octaves => octaves,
+    };
+
+    let lacunarity = match settings.lacunarity {
+        Some(lacunarity) if !(lacunarity >= 1.0 && lacunarity.is_finite()) => {
+            println!("Warning: Lacunarity must be at least 1. Using the default of the noise instead.");
+            None
+        }
+        lacunarity => lacunarity,
     };
 
-    let seed = args.seed;
+    let persistence = match settings.persistence {
+        Some(persistence) if !(persistence > 0.0 && persistence <= 1.0) => {
+            println!("Warning: Persistence must be above 0 and at most 1. Using the default of the noise instead.");
+            None
+        }
+        persistence => persistence,
+    };
+
+    let noise_params = NoiseParams { frequency, octaves, lacunarity, persistence };
+
+    let mut seed = settings.seed.unwrap_or(defaults.seed);
+
+    // The configuration file may replace fields of the definitions
+    let material_overrides = settings.material_overrides.clone().unwrap_or_default();
+    let effect_overrides = settings.effect_overrides.clone().unwrap_or_default();
+
+    let materials = match MaterialRegistry::from_file(&materials_path, &material_overrides) {
+        Ok(materials) => materials,
+        Err(e) => {
+            println!("Warning: Could not load materials ({}). Using built-in materials instead.", e);
+            MaterialRegistry::builtin(&material_overrides)
+                .map_err(|e| GameError::CustomError(format!("Could not override the built-in materials ({})", e)))?
+        }
+    };
 
-    // Update constants
-    update_constants(width, height, delta, cell_size, seed, noise);
+    let effects = match EffectRegistry::from_file(&effects_path, &effect_overrides) {
+        Ok(effects) => effects,
+        Err(e) => {
+            println!("Warning: Could not load effects ({}). Using built-in effects instead.", e);
+            EffectRegistry::builtin(&effect_overrides)
+                .map_err(|e| GameError::CustomError(format!("Could not override the built-in effects ({})", e)))?
+        }
+    };
+
+    let layers = match TerrainLayers::from_file(&layers_path, &materials) {
+        Ok(layers) => layers,
+        Err(e) => {
+            println!("Warning: Could not load terrain layers ({}). Using built-in layers instead.", e);
+            TerrainLayers::builtin(&materials)
+                .map_err(|e| GameError::CustomError(format!("Could not build the built-in terrain layers ({})", e)))?
+        }
+    };
+
+    let noise_graph = match settings.noise_graph.as_deref().map(NoiseGraph::from_file) {
+        Some(Ok(graph)) => Some(graph),
+        Some(Err(e)) => {
+            println!("Warning: Could not load the noise graph ({}). Using the {} noise instead.", e, noise);
+            None
+        }
+        None => None,
+    };
+    let mode = settings.mode.unwrap_or(defaults.mode);
+
+    // A saved terrain dictates the world dimensions, cell size, seed, generation mode, noise type and noise parameters
+    let snapshot = match args.load.as_deref().map(TerrainSnapshot::load) {
+        Some(Ok(snapshot)) => Some(snapshot),
+        Some(Err(e)) => {
+            println!("Warning: Could not load the terrain ({}). Using a generated terrain instead.", e);
+            None
+        }
+        None => None,
+    };
+    let (world_width, world_height, cell_size, mode, noise, noise_params) = match &snapshot {
+        Some(snapshot) => {
+            seed = snapshot.seed;
+            (
+                Some(snapshot.terrain_width),
+                Some(snapshot.terrain_height),
+                snapshot.cell_size,
+                snapshot.mode,
+                snapshot.noise_type,
+                snapshot.noise_params,
+            )
+        }
+        None => (world_width, world_height, cell_size, mode, noise, noise_params),
+    };
+
+    // A replayed session dictates the world dimensions, cell size, delta time, seed, generation mode, noise and passes
+    let replay = match args.replay.as_deref().map(Replay::load) {
+        Some(Ok(replay)) => Some(replay),
+        Some(Err(e)) => {
+            println!("Warning: Could not load the replay ({}). Starting a new session instead.", e);
+            None
+        }
+        None => None,
+    };
+    let (world_width, world_height, delta, cell_size, mode, noise, noise_params, falling_sand, collapse) = match &replay {
+        Some(replay) => {
+            seed = replay.seed;
+            (
+                Some(replay.terrain_width),
+                Some(replay.terrain_height),
+                (1.0 / replay.delta).round() as u32,
+                replay.cell_size,
+                replay.mode,
+                replay.noise_type,
+                replay.noise_params,
+                replay.falling_sand,
+                replay.collapse,
+            )
+        }
+        None => (
+            world_width,
+            world_height,
+            delta,
+            cell_size,
+            mode,
+            noise,
+            noise_params,
+            settings.falling_sand.unwrap_or(defaults.falling_sand),
+            settings.collapse.unwrap_or(defaults.collapse),
+        ),
+    };
+    if noise_graph.is_some() && mode == GenerationMode::Sidescroller {
+        println!("Warning: The noise graph is not used by the sidescroller mode. Using the {} noise instead.", noise);
+    }
+
+    // Gather the settings of the world
+    let config = build_config(width, height, world_width, world_height, delta, cell_size, seed, mode, noise, noise_params, falling_sand, collapse);
+
+    // Print or save the effective configuration without opening a window
+    if let Some(Command::PrintConfig { output }) = &args.command {
+        let effective = ConfigFile {
+            width: Some(width),
+            height: Some(height),
+            delta: Some(delta),
+            cellsize: Some(cell_size),
+            world_width,
+            world_height,
+            seed: Some(seed),
+            noise: Some(noise),
+            mode: Some(mode),
+            frequency: Some(noise_params.frequency),
+            octaves: noise_params.octaves,
+            lacunarity: noise_params.lacunarity,
+            persistence: noise_params.persistence,
+            noise_graph: settings.noise_graph,
+            falling_sand: Some(falling_sand),
+            collapse: Some(collapse),
+            materials: Some(materials_path),
+            effects: Some(effects_path),
+            layers: Some(layers_path),
+            material_overrides: settings.material_overrides,
+            effect_overrides: settings.effect_overrides,
+        };
+        match output {
+            Some(path) => {
+                effective.save(path).map_err(GameError::CustomError)?;
+                println!("Configuration saved to {}.", path);
+            }
+            None => print!("{}", effective.to_toml().map_err(GameError::CustomError)?),
+        }
+        return Ok(());
+    }
+
+    // The sidescroller mode lays out grass and rock, whatever the materials file
+    if config.mode == GenerationMode::Sidescroller {
+        ground_materials(&materials).map_err(|e| GameError::CustomError(format!("Could not lay out the sidescroller terrain ({})", e)))?;
+    }
+
+    // Import the terrain from an image, at the final terrain dimensions
+    let base_terrain = match args.terrain_image.as_deref().map(|path| import_terrain_image(path, config.terrain_width, config.terrain_height, &materials, &layers, args.image_mode)) {
+        Some(Ok(terrain)) => Some(terrain),
+        Some(Err(e)) => {
+            println!("Warning: Could not import the terrain image ({}). Using a generated terrain instead.", e);
+            None
+        }
+        None => None,
+    };
+
+    // Export the terrain without opening a window
+    if let Some(Command::Export { output, full_scale }) = &args.command {
+        let mut simulation = Simulation::new(&config, noise_graph, layers, materials, effects);
+        if let Some(terrain) = base_terrain
+            && let Err(e) = simulation.set_terrain(terrain)
+        {
+            println!("Warning: Could not use the imported terrain ({}). Using a generated terrain instead.", e);
+        }
+        if let Some(snapshot) = &snapshot
+            && let Err(e) = simulation.restore(snapshot)
+        {
+            println!("Warning: Could not load the terrain ({}). Using a generated terrain instead.", e);
+        }
+
+        let scale = if *full_scale { config.cell_size as u32 } else { 1 };
+        let world = (0..simulation.terrain_width(), 0..simulation.terrain_height());
+        export_terrain_image(&simulation, output, world, scale, false).map_err(GameError::CustomError)?;
+        println!("Terrain exported to {}.", output);
+        return Ok(());
+    }
 
     // Create a new context and event loop
     let cb = ContextBuilder::new("Terrain Destruction", "DIARRA&SERRANO")
         .window_setup(ggez::conf::WindowSetup::default().title("Terrain Destruction"))
         .window_mode(
             ggez::conf::WindowMode::default()
-                .dimensions(*SCREEN_WIDTH.read().unwrap(), *SCREEN_HEIGHT.read().unwrap())
-                .resizable(false),
+                .dimensions(config.screen_width, config.screen_height)
+                .min_dimensions(MIN_WIDTH as f32, MIN_HEIGHT as f32)
+                .resizable(true),
         );
 
     // Build the context and event loop
     let (ctx, event_loop) = cb.build()?;
-    let state = MainState::new(&ctx)?;
+    // The world follows the window unless its size was given, loaded or replayed, or the terrain imported
+    let fit_world = world_width.is_none() && world_height.is_none() && base_terrain.is_none() && replay.is_none();
+    let session = SessionOptions {
+        base_terrain,
+        snapshot,
+        save_on_exit: args.save_on_exit,
+        record: args.record,
+        replay,
+        fit_world,
+    };
+    let state = MainState::new(&ctx, config, materials, noise_graph, layers, effects, session)?;
 
     // Run the event loop
     event::run(ctx, event_loop, state)
@@ -4,136 +4,217 @@
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: March 15, 2025
-// Last modified: March 26, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
-use ggez::{Context, ContextBuilder, GameError, GameResult};
-use ggez::event::{self, EventHandler, MouseButton};
+use ggez::{Context, GameError, GameResult};
+use ggez::event::{EventHandler, MouseButton};
+use ggez::input::keyboard::{KeyCode, KeyInput, KeyMods};
 use ggez::graphics::{
     Image, Canvas, Color, DrawMode, DrawParam, Mesh, Rect, Text, TextFragment, Drawable, InstanceArray
 };
-use ggez::audio::{Source, SoundSource};
-use noise::{NoiseFn, Perlin, Fbm};
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::cell;
-use std::f32::consts::TAU;
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::{Arc, RwLock};
-use std::collections::HashMap;
-use rand::Rng;
+use std::sync::Arc;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
-use clap::Parser;
-
-use rayon::prelude::*;
-use std::sync::{Mutex, atomic::{AtomicUsize, Ordering}};
-
+use clap::ValueEnum;
 
-use crate::{read_cell_size, read_delta, read_noisetype, read_screen_height, read_screen_width, read_seed, read_terrain_height, read_terrain_width};
+use crate::effect::{EffectId, EffectRegistry, Shape};
+use crate::camera::Camera;
 use crate::cell::Cell;
-use crate::effect::{Effect, EffectType};
-use crate::materials::Material;
-use crate::quadtree;
+use crate::chunks::CHUNK_SIZE;
+use crate::config::Config;
+use crate::layers::TerrainLayers;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::noisegraph::NoiseGraph;
 use crate::noisetypes::NoiseType;
-use crate::noisegenerator::NoiseGenerator;
+use crate::replay::{REPLAY_VERSION, Replay, ReplayAction};
+use crate::save::TerrainSnapshot;
+use crate::terrainimage::export_terrain_image;
+use crate::simulation::{Simulation, SimulationEvent};
+
+// Radius of the liquid poured with the right mouse button, in cells
+const LIQUID_POUR_RADIUS: usize = 3;
+
+// Speed of the camera moved with the WASD keys, in screen pixels per second
+const CAMERA_PAN_SPEED: f32 = 600.0;
+
+// Zoom factor of one step of the mouse wheel
+const ZOOM_STEP: f32 = 1.25;
+
+// Smallest and largest cell sizes reached with the C key, and the step between them, in pixels
+const MIN_CELL_SIZE: f32 = 5.0;
+const MAX_CELL_SIZE: f32 = 20.0;
+const CELL_SIZE_STEP: f32 = 1.0;
+
+// Duration of the flash drawn for an explosion, in seconds
+const BLAST_FLASH_DURATION: f32 = 0.3;
+
+// File written and read by the Save and Load buttons
+const QUICKSAVE_PATH: &str = "quicksave.tds";
+
+// Size and layout of the UI buttons
+const BUTTON_WIDTH: f32 = 100.0;
+const BUTTON_HEIGHT: f32 = 40.0;
+const BUTTON_SPACING: f32 = 10.0;
+const BUTTON_MARGIN: f32 = 10.0;
+
+// The action triggered by a UI button
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ButtonAction {
+    Reset,
+    Save,
+    Load,
+    Select(EffectId),
+}
+
+/// The options of a game session, given on the command line.
+///
+/// # Fields
+/// - `base_terrain`: A terrain imported from an image, used instead of a generated one.
+/// - `snapshot`: A saved terrain to start from.
+/// - `save_on_exit`: The file the terrain is saved to when the game quits.
+/// - `record`: The file the session is recorded to when the game quits.
+/// - `replay`: A recorded session to replay.
+/// - `fit_world`: Whether the world follows the size of the window, rather than having its own.
+#[derive(Default)]
+pub struct SessionOptions {
+    pub base_terrain: Option<Vec<Vec<Cell>>>,
+    pub snapshot: Option<TerrainSnapshot>,
+    pub save_on_exit: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<Replay>,
+    pub fit_world: bool,
+}
 
 /// The `MainState` struct represents the main game state for the Terrain Destruction game.
-/// It manages the terrain, effects, UI, audio, and game logic.
+/// It renders the simulation, and manages the UI and audio.
 ///
 /// # Fields
-/// - `terrain`: A 2D vector representing the terrain grid, where each cell is of type `Cell`.
-/// - `effects`: A vector of active effects in the game.
-/// - `seed`: The seed used for terrain generation.
-/// - `noise_generator`: An instance of `NoiseGenerator` for generating terrain noise.
+/// - `simulation`: The headless `Simulation` holding the terrain, effects and destruction logic.
 /// - `input_seed`: A string representing the user-provided seed for terrain generation.
 /// - `is_focused_input`: A boolean indicating whether the input field is focused.
-/// - `screen_width`: The width of the game screen.
-/// - `screen_height`: The height of the game screen.
+/// - `config`: The settings of the session: the size of the game screen, the time step and the settings of the world.
+/// - `camera`: The camera over the world, panned and zoomed by the user.
 /// - `selected_effect`: The currently selected effect type (e.g., Bubbles, Lightning).
+/// - `selected_liquid`: The liquid material poured with the right mouse button, if the registry has any.
 /// - `_stream`: The audio output stream for sound playback.
 /// - `stream_handle`: A handle to the audio output stream for managing audio sinks.
 /// - `sinks`: A vector of audio sinks for concurrent sound playback.
-/// - `terrain_quadtree`: A quadtree for efficient spatial queries on the terrain.
-/// - `quadtree_dirty`: A flag indicating whether the quadtree needs to be updated.
 /// - `intro_timer`: A timer for displaying the introduction screen.
 /// - `show_intro`: A boolean indicating whether the introduction screen is active.
-/// - `lightning_mesh`: A mesh representing the lightning effect.
-/// - `bubble_mesh`: A mesh representing the bubble effect.
-/// - `more_bubble_mesh`: A mesh representing the more bubbles effect.
-/// - `instances`: An instance array for rendering terrain and effects efficiently.
+/// - `base_terrain`: The imported terrain restored by the Reset button, if the terrain was imported from an image.
+/// - `save_on_exit`: The file the terrain is saved to when the game quits, if any.
+/// - `record`: The file the session is recorded to when the game quits, with the recorded session.
+/// - `replay`: The session being replayed, if any. User input is ignored until it ends.
+/// - `fit_world`: Whether the world is resized with the window. Otherwise, resizing the window shows more or less of the world.
+/// - `effect_meshes`: The mesh of each effect type, indexed by `EffectId`, built from its visual.
+/// - `blasts`: The recent explosions, as `(x, y, radius, remaining time)`, drawn as fading flashes.
+/// - `cell_image`: The white square of one cell, tinted by the terrain instances.
+/// - `drawn_cell_size`: The cell size the effect meshes, the cell image and the instance arrays were built for.
+/// - `chunk_instances`: An instance array per loaded chunk for rendering terrain efficiently, so that only the chunks
+///   that changed are uploaded again.
+/// - `debris_instances`: An instance array for rendering falling debris.
 ///
 /// # Methods
-/// - `new() -> GameResult<MainState>`
+/// - `new(ctx: &Context, config: Config, materials: MaterialRegistry, behaviors: EffectRegistry, session: SessionOptions) -> GameResult<MainState>`
 ///   Creates a new instance of `MainState` and initializes the game state.
-/// - `generate_terrain(&mut self)`
-///   Generates the terrain using Perlin noise and rebuilds the quadtree.
-/// - `damage_terrain_at(&mut self, x: usize, y: usize, amount: f32, ignore_durability: bool)`
-///   Damages the terrain at the specified coordinates, optionally ignoring durability.
-/// - `spawn_effect(&mut self, x: f32, y: f32)`
-///   Spawns a new effect at the specified position.
-/// - `update_effects(&mut self, ctx: &mut Context, dt: f32) -> GameResult`
-///   Updates the active effects, processes damage requests, and handles sound playback.
+/// - `process_simulation_events(&mut self)`
+///   Applies the events produced by the simulation to the renderer and the audio.
+/// - `update_chunk_instances(&mut self, ctx: &Context)`
+///   Builds the instance arrays of the loaded chunks that have none.
+/// - `update_cell_size(&mut self, ctx: &Context) -> GameResult`
+///   Builds the drawing resources sized in cells again after a change of cell size.
+/// - `fit_world_to_window(&mut self)`
+///   Resizes the world to the window, if it follows the window.
 /// - `play_sound(&mut self, sound_path: &str, volume: f32)`
 ///   Plays a sound from the specified file path at the given volume.
-/// - `update_quadtree_if_needed(&mut self)`
-///   Rebuilds the quadtree if it is marked as dirty.
+/// - `perform(&mut self, action: ReplayAction)`
+///   Applies an action of the user to the simulation, recording it if the session is recorded.
+/// - `buttons(&self) -> Vec<(String, ButtonAction, Rect)>`
+///   Lays out the UI buttons.
+/// - `save_terrain(&self, path: &str)` / `load_terrain(&mut self, path: &str)`
+///   Saves the terrain to, or restores it from, a file.
 ///
 /// # Event Handling
 /// Implements `EventHandler<GameError>` for handling game events:
 /// - `update(&mut self, ctx: &mut Context) -> GameResult`
-///   Updates the game state, including effects and audio sinks.
+///   Pans the camera with the WASD keys, steps the simulation around the camera's view and updates the audio sinks.
 /// - `draw(&mut self, ctx: &mut Context) -> GameResult`
-///   Renders the game state, including terrain, effects, and UI.
+///   Renders the game state, including terrain and effects through the camera, and UI.
 /// - `mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult`
-///   Handles mouse input for spawning effects or interacting with UI buttons.
+///   Handles mouse input for spawning effects, pouring liquids (at the world position under the cursor) or interacting
+///   with UI buttons.
+/// - `mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) -> GameResult`
+///   Pans the camera while the middle mouse button is held.
+/// - `mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) -> GameResult`
+///   Zooms the camera in or out around the cursor.
+/// - `key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult`
+///   Handles keyboard shortcuts: N switches the noise, L the liquid poured, C the cell size, F12 exports a screenshot
+///   of the terrain and effects, and Shift+F12 exports the terrain alone at one pixel per cell.
+/// - `quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool>`
+///   Saves the terrain and the recorded session if requested, then lets the game quit.
+/// - `resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult`
+///   Follows the new window size: resizes the world if it fits the window, and the camera's view.
 pub struct MainState {
-    // Terrain
-    terrain: Vec<Vec<Cell>>,
-
-    // Effects
-    effects: Vec<Effect>,
-
-    // Terrain generation
-    seed: i64,
-    noise_generator: NoiseGenerator,
+    // Simulation
+    simulation: Simulation,
 
     // UI
     input_seed: String,
     is_focused_input: bool,
 
-    // Dimensions
-    screen_width: f32,
-    screen_height: f32,
+    // Settings of the session, including the screen dimensions
+    config: Config,
+
+    // Camera over the world
+    camera: Camera,
 
     // Selected effect
-    selected_effect: EffectType, // Track the currently selected effect
+    selected_effect: EffectId, // Track the currently selected effect
+
+    // Liquid poured with the right mouse button
+    selected_liquid: Option<MaterialId>,
 
     // Audio-related fields
     _stream: OutputStream,
     stream_handle: Arc<OutputStreamHandle>,
     sinks: Vec<Arc<Sink>>, // Store sinks for concurrent playback
 
-    // New: quadtree for terrain cells
-    terrain_quadtree: quadtree::QuadTree,
-    
-    // Flag to track if the quadtree needs updating
-    quadtree_dirty: bool,
-
     // Timer for the introduction screen
     intro_timer: f32, 
     
     // Whether the introduction screen is active
     show_intro: bool, 
 
-    // Meshes for terrain and effects
-    lightning_mesh: Mesh,
-    bubble_mesh: Mesh,
-    more_bubble_mesh: Mesh,
+    // Imported terrain, restored on reset instead of generating one
+    base_terrain: Option<Vec<Vec<Cell>>>,
+
+    // File the terrain is saved to when quitting
+    save_on_exit: Option<String>,
+
+    // Session recording and replay
+    record: Option<(String, Replay)>,
+    replay: Option<Replay>,
+
+    // Whether the world follows the window size
+    fit_world: bool,
+
+    // Meshes for the effects
+    effect_meshes: Vec<Mesh>,
+
+    // Recent explosions, drawn as fading flashes
+    blasts: Vec<(f32, f32, f32, f32)>,
+
+    // Instance arrays for the terrain chunks and the falling debris
+    cell_image: Image,
+    chunk_instances: HashMap<(usize, usize), InstanceArray>,
+    debris_instances: InstanceArray,
 
-    // Instance arrays for grass and rock
-    instances: InstanceArray,
+    // Cell size the resources above were built for
+    drawn_cell_size: f32,
 }
 
 
@@ -142,42 +223,42 @@ pub struct MainState {
 /// ## `new`
 /// Initializes a new instance of `MainState`.
 /// - Sets up the audio output stream.
-/// - Initializes the terrain and quadtree.
+/// - Creates the simulation, which generates the initial terrain.
+/// - Builds one mesh per effect type from its visual.
+/// - When replaying, reseeds the simulation as in the recorded session and regenerates the terrain.
+/// - Uses the imported terrain, if any, then restores the terrain from a snapshot, if one was loaded.
+/// - Starts recording the session, if requested.
 /// - Configures various game settings like screen dimensions, selected effects, and intro timer.
-/// - Generates the initial terrain using Perlin noise.
 ///
-/// ## `generate_terrain`
-/// Generates the terrain using Perlin noise and populates the quadtree with terrain cells.
-/// - Assigns materials and durability to each cell based on Perlin noise values.
-/// - Rebuilds the quadtree to reflect the updated terrain.
+/// ## `process_simulation_events`
+/// Consumes the events produced by the simulation.
+/// - Updates the instance of every changed cell.
+/// - Drops the instances of every chunk when the terrain has been regenerated, and of the chunks loaded or unloaded,
+///   so that they are built again before being drawn.
+/// - Plays the requested sounds.
 ///
-/// ## `damage_terrain_at`
-/// Damages the terrain at a specific cell.
-/// - Parameters:
-///   - `x`: The x-coordinate of the cell.
-///   - `y`: The y-coordinate of the cell.
-///   - `amount`: The amount of damage to apply.
-///   - `ignore_durability`: If `true`, the cell is destroyed regardless of its durability.
-/// - Marks the quadtree as dirty if a cell is modified.
-/// - Returns the material of the cell after damage is applied.
+/// ## `update_chunk_instances`
+/// Fills an instance array for each loaded chunk without one, with one instance per cell, colored by its material's color.
 ///
-/// ## `spawn_effect`
-/// Spawns a new effect at a specified position.
-/// - Parameters:
-///   - `x`: The x-coordinate of the effect's position.
-///   - `y`: The y-coordinate of the effect's position.
-/// - Adds the effect to the list of active effects.
+/// ## `update_cell_size`
+/// Builds the effect meshes, the cell image and the debris instances again at the cell size of the simulation, if it changed
+/// since they were built, and drops the chunk instances so that they are built again from the new cell image.
 ///
-/// ## `update_effects`
-/// Updates the state of all active effects.
-/// - Parameters:
-///   - `ctx`: The game context.
-///   - `dt`: The delta time since the last update.
-/// - Handles effect movement, collision detection, and interactions with the terrain.
-/// - Spawns sub-effects for certain effect types (e.g., bubbles).
-/// - Plays sounds for specific events (e.g., bouncing off edges).
-/// - Applies damage to terrain cells affected by effects.
-/// - Removes expired effects and ensures the quadtree is up-to-date.
+/// ## `fit_world_to_window`
+/// Resizes the world to as many cells as fit in the window, unless the world has its own size or a replay dictates it.
+///
+/// ## `perform`
+/// Applies an action of the user (selecting, spawning, pouring, resetting, switching the noise, resizing the world or the cells),
+/// from the input or from a replay, and records it with the current tick when the session is recorded. The camera stays over the
+/// same cells when they are resized.
+///
+/// ## `buttons`
+/// Lays out the UI buttons (Reset, Save, Load, then one per effect type) from left to right,
+/// wrapping to a new row when the screen is full, so that they follow the size of the window.
+///
+/// ## `save_terrain` / `load_terrain`
+/// Saves the terrain to a file, or restores it from a file, printing a warning on failure.
+/// Loading is refused while the session is recorded, since the replay could not reproduce it.
 ///
 /// ## `play_sound`
 /// Plays a sound effect.
@@ -185,374 +266,278 @@ pub struct MainState {
 ///   - `sound_path`: The file path to the sound resource.
 ///   - `volume`: The volume level (0.0 = mute, 1.0 = full volume).
 /// - Creates a new audio sink for the sound and stores it to keep it alive.
-///
-/// ## `update_quadtree_if_needed`
-/// Rebuilds the quadtree if it is marked as dirty.
-/// - Iterates through the terrain and inserts non-air cells into the quadtree.
-/// - Resets the dirty flag after rebuilding.
 impl MainState {
-    pub fn new(ctx: &Context) -> GameResult<MainState> {
+    pub fn new(ctx: &Context, config: Config, materials: MaterialRegistry, noise_graph: Option<NoiseGraph>, layers: TerrainLayers, behaviors: EffectRegistry, session: SessionOptions) -> GameResult<MainState> {
         let (_stream, stream_handle) = OutputStream::try_default().expect("Failed to create audio output stream");
-        
-        // Initialize the quadtree covering the entire terrain area
-        let qt_boundary = Rect::new(0.0, 0.0, read_terrain_width() as f32 * read_cell_size(), read_terrain_height() as f32 * read_cell_size());
-        
+
+        // Build the mesh of each effect type, its visual being sized in cells
+        let cell_size = config.cell_size;
+        let effect_meshes = build_effect_meshes(ctx, &behaviors, cell_size)?;
+
+        let simulation = Simulation::new(&config, noise_graph, layers, materials, behaviors);
+        let camera = Camera::new(config.screen_width, config.screen_height, simulation.width(), simulation.height());
+        let selected_liquid = simulation.materials().liquids().first().copied();
+
         let mut s = MainState {
-            terrain: vec![vec![
-                Cell { material: Material::Air, durability: 0.0 }; read_terrain_height()
-            ]; read_terrain_width()],
-            effects: vec![],
-            seed: read_seed(),
-            noise_generator: NoiseGenerator::new(read_noisetype(), read_seed() as u32),
+            simulation,
             input_seed: String::new(),
             is_focused_input: false,
-            screen_width: read_screen_width(),
-            screen_height: read_screen_height(),
-            selected_effect: EffectType::Bubbles,
+            config,
+            camera,
+            selected_effect: EffectId(0),
+            selected_liquid,
             _stream,
             stream_handle: Arc::new(stream_handle),
             sinks: Vec::new(),
-            terrain_quadtree: quadtree::QuadTree::new(qt_boundary, 4),
-            quadtree_dirty: false, // Initialize the flag
             intro_timer: 3.0, // Show the intro for 3 seconds
             show_intro: true, // Start with the introduction screen
-            lightning_mesh: Mesh::new_rectangle(
-                ctx,
-                DrawMode::fill(),
-                Rect::new(-30.0 / 2.0, -10.0 / 2.0, 30.0, 10.0),
-                Color::from_rgb(255, 255, 0),
-            )?,
-            bubble_mesh: Mesh::new_circle(
-                ctx,
-                DrawMode::fill(),
-                ggez::mint::Point2 { x: 0.0, y: 0.0 },
-                read_cell_size(),
-                0.5,
-                Color::RED,
-            )?,
-            more_bubble_mesh: Mesh::new_circle(
-                ctx,
-                DrawMode::fill(),
-                ggez::mint::Point2 { x: 0.0, y: 0.0 },
-                read_cell_size(),
-                0.5,
-                Color::from_rgb(0, 0, 0),
-            )?,
-            instances: InstanceArray::new(ctx,Image::from_color(ctx, read_cell_size() as u32, read_cell_size() as u32, Some(Color::from_rgb(255, 255, 255)))),
+            base_terrain: session.base_terrain,
+            save_on_exit: session.save_on_exit,
+            record: None,
+            replay: None,
+            fit_world: session.fit_world,
+            effect_meshes,
+            blasts: Vec::new(),
+            cell_image: Image::from_color(ctx, cell_size as u32, cell_size as u32, Some(Color::from_rgb(255, 255, 255))),
+            chunk_instances: HashMap::new(),
+            debris_instances: InstanceArray::new(ctx,Image::from_color(ctx, cell_size as u32, cell_size as u32, Some(Color::from_rgb(255, 255, 255)))),
+            drawn_cell_size: cell_size,
         };
-        s.generate_terrain();
-        Ok(s)
-    }
 
-    // Generate the terrain using Perlin noise
-    fn generate_terrain(&mut self) {
+        // Keep the chunks on screen loaded
+        s.simulation.set_view(s.camera.view());
 
-        // Clear the terrain and instance arrays
-        self.instances.clear();
+        // Replay the session from the same random stream
+        if let Some(replay) = &session.replay {
+            s.simulation.reseed(replay.rng_seed);
+            s.simulation.generate_terrain();
+        }
 
-        // Generate the terrain using Perlin noise
-        let actual_seed = if self.seed == -1 {
-            rand::rng().random_range(0..100_000)
-        } else {
-            self.seed as u32
-        };
+        if let Some(terrain) = s.base_terrain.clone()
+            && let Err(e) = s.simulation.set_terrain(terrain)
+        {
+            println!("Warning: Could not use the imported terrain ({}). Using a generated terrain instead.", e);
+        }
+        if let Some(snapshot) = session.snapshot
+            && let Err(e) = s.simulation.restore(&snapshot)
+        {
+            println!("Warning: Could not load the terrain ({}). Using a generated terrain instead.", e);
+        }
 
-        // Update the seed
-        self.noise_generator.generate(actual_seed);
-    
-        // Generate the terrain based on Perlin noise
-        let scale = 0.05;
-        let terrain_width = read_terrain_width();
-        let terrain_height = read_terrain_height();
-    
-        // Iterate over each cell in the terrain
-        for x in 0..terrain_width {
-            for y in 0..terrain_height {
-                let nx = x as f64 * scale;
-                let ny = y as f64 * scale;
-                let val = self.noise_generator.get(nx, ny);
-
-                // Assign material and durability based on the noise value
-                let (mat, dura) = if val < -0.2 {
-                    (Material::Air, 0.0)
-                } else if val < 0.2 {
-                    (Material::Grass, 1.0)
-                } else {
-                    (Material::Rock, 8.0)
-                };
+        // Record the session from its initial state
+        if let Some(path) = session.record {
+            let replay = Replay {
+                version: REPLAY_VERSION,
+                terrain_width: s.simulation.terrain_width(),
+                terrain_height: s.simulation.terrain_height(),
+                cell_size: s.simulation.cell_size(),
+                delta: s.config.delta,
+                seed: s.config.seed,
+                mode: s.config.mode,
+                noise_type: s.config.noise_type,
+                noise_params: s.config.noise_params,
+                rng_seed: s.simulation.rng_seed(),
+                falling_sand: s.config.falling_sand,
+                collapse: s.config.collapse,
+                events: Vec::new(),
+            };
+            s.record = Some((path, replay));
+        }
+        s.replay = session.replay;
 
-                // Update the cell in the terrain
-                self.terrain[x][y] = Cell { material: mat, durability: dura };
+        s.process_simulation_events();
+        Ok(s)
+    }
 
-                let dest = ggez::mint::Point2 {
-                    x: x as f32 * read_cell_size(),
-                    y: y as f32 * read_cell_size(),
-                };
-                let dp = DrawParam::default().dest(dest);
-                match mat {
-                    Material::Grass => {
-                        self.instances.push(dp.color(Color::from_rgb(111, 171, 51)));
-                    }
-                    Material::Rock => {
-                        self.instances.push(dp.color(Color::from_rgb(123, 108, 113)));
-                    }
-                    _ => {
-                        self.instances.push(dp.color(Color::from_rgb(255, 255, 255)));
+    // Apply the events produced by the simulation
+    fn process_simulation_events(&mut self) {
+        for event in self.simulation.drain_events() {
+            match event {
+                SimulationEvent::CellChanged { x, y } => {
+                    // Cells are in column-major order within their chunk
+                    let (cx, cy) = (x / CHUNK_SIZE, y / CHUNK_SIZE);
+                    let (columns, rows) = self.simulation.terrain().chunk_bounds(cx, cy);
+                    let index = (x - columns.start) * rows.len() + (y - rows.start);
+                    let dp = self.cell_draw_param(x, y);
+                    if let Some(instances) = self.chunk_instances.get_mut(&(cx, cy)) {
+                        instances.update(index as u32, dp);
                     }
                 }
+                SimulationEvent::TerrainGenerated => self.chunk_instances.clear(),
+                SimulationEvent::ChunkLoaded { cx, cy } | SimulationEvent::ChunkUnloaded { cx, cy } => {
+                    self.chunk_instances.remove(&(cx, cy));
+                }
+                SimulationEvent::Sound { path, volume } => self.play_sound(&path, volume),
+                SimulationEvent::Explosion { x, y, radius } => self.blasts.push((x, y, radius, BLAST_FLASH_DURATION)),
             }
         }
-    
-        // Rebuild the quadtree for the terrain.
-        let boundary = Rect::new(0.0, 0.0, terrain_width as f32 * read_cell_size(), terrain_height as f32 * read_cell_size());
-
-        // Increase capacity
-        self.terrain_quadtree = quadtree::QuadTree::new(boundary, 8); 
+    }
 
-        // Insert cells into the quadtree
-        for tx in 0..terrain_width {
-            for ty in 0..terrain_height {
-                // Compute center of the cell.
-                let cx = tx as f32 * read_cell_size() + read_cell_size() / 2.0;
-                let cy = ty as f32 * read_cell_size() + read_cell_size() / 2.0;
+    // Build the instance arrays of the loaded chunks that have none
+    fn update_chunk_instances(&mut self, ctx: &Context) {
+        for (cx, cy) in self.simulation.terrain().loaded() {
+            if self.chunk_instances.contains_key(&(cx, cy)) {
+                continue;
+            }
 
-                // Insert the cell into the quadtree
-                self.terrain_quadtree.insert(quadtree::QuadTreeItem { x: cx, y: cy, tx, ty });
+            // One instance per cell, in column-major order
+            let mut instances = InstanceArray::new(ctx, self.cell_image.clone());
+            let (columns, rows) = self.simulation.terrain().chunk_bounds(cx, cy);
+            for x in columns {
+                for y in rows.clone() {
+                    instances.push(self.cell_draw_param(x, y));
+                }
             }
+            self.chunk_instances.insert((cx, cy), instances);
         }
     }
 
-    // Damage the terrain at the specified position
-    fn damage_terrain_at(&mut self, x: usize, y: usize, amount: f32, ignore_durability: bool) -> Material {
-        // Check if the position is within the terrain bounds
-        
-        let should_play_sound = self.terrain[x][y].durability - amount <= 0.0;
-
-        // Play sound if the cell is not air and the durability will be 0
-        if self.terrain[x][y].material != Material::Air && (should_play_sound || ignore_durability) && rand::rng().random_bool(0.10) {
-            if self.terrain[x][y].material == Material::Grass {
-                self.play_sound("resources/sounds/grass.ogg", 0.2);
-            } else if self.terrain[x][y].material == Material::Rock {
-                self.play_sound("resources/sounds/stone.ogg", 0.2);
-            }
+    // Build the drawing resources sized in cells again after a change of cell size
+    fn update_cell_size(&mut self, ctx: &Context) -> GameResult {
+        let cell_size = self.simulation.cell_size();
+        if cell_size == self.drawn_cell_size {
+            return Ok(());
         }
 
-        let cell = &mut self.terrain[x][y];
-        let old_type = cell.material;
-        
-        if x < read_terrain_width() && y < read_terrain_height() {
-
-            let white_dp = DrawParam::default().dest(ggez::mint::Point2 {
-                x: x as f32 * read_cell_size(),
-                y: y as f32 * read_cell_size(),
-            }).color(Color::WHITE);
-
-            // Apply damage if the cell is not air
-            if cell.material != Material::Air {
-                if ignore_durability {
-                    self.instances.update((x * read_terrain_height() + y) as u32, white_dp);
-
-                    cell.material = Material::Air;
-                    cell.durability = 0.0;
-                } else {
-                    cell.durability -= amount;
-                    if cell.durability <= 0.0 {
-
-                        self.instances.update((x * read_terrain_height() + y) as u32, white_dp);
+        self.effect_meshes = build_effect_meshes(ctx, self.simulation.behaviors(), cell_size)?;
+        self.cell_image = Image::from_color(ctx, cell_size as u32, cell_size as u32, Some(Color::from_rgb(255, 255, 255)));
+        self.debris_instances = InstanceArray::new(ctx, self.cell_image.clone());
+        self.chunk_instances.clear();
+        self.drawn_cell_size = cell_size;
+        Ok(())
+    }
 
-                        cell.material = Material::Air;
-                        cell.durability = 0.0;
-                    }
-                }
-                // Mark the quadtree as dirty
-                self.quadtree_dirty = true; 
+    // Resize the world to the window, if it follows the window
+    fn fit_world_to_window(&mut self) {
+        if self.fit_world && self.replay.is_none() {
+            let cell_size = self.simulation.cell_size();
+            let (terrain_width, terrain_height) = ((self.config.screen_width / cell_size) as usize, (self.config.screen_height / cell_size) as usize);
+            if (terrain_width, terrain_height) != (self.simulation.terrain_width(), self.simulation.terrain_height()) {
+                self.perform(ReplayAction::Resize { width: terrain_width, height: terrain_height });
             }
-            cell.material
-        } else {
-            Material::Air
         }
     }
 
-    // Spawn a new effect at the specified position
-    fn spawn_effect(&mut self, x: f32, y: f32) {
-
-        // Add a new effect to the list
-        self.effects.push(Effect {
-            effect_type: self.selected_effect,
-            position: (x, y),
-            direction: rand::rng().random_range(0.0..TAU),
-            started_at: Instant::now(),
-            spawned: false,
-        });
+    // Compute the draw parameters of a terrain cell
+    fn cell_draw_param(&self, x: usize, y: usize) -> DrawParam {
+        let cell_size = self.simulation.cell_size();
+        let dest = ggez::mint::Point2 {
+            x: x as f32 * cell_size,
+            y: y as f32 * cell_size,
+        };
+        let material = self.simulation.terrain().get(x, y).map_or(MaterialId::AIR, |cell| cell.material);
+        let [r, g, b] = self.simulation.materials().get(material).color;
+        DrawParam::default().dest(dest).color(Color::from_rgb(r, g, b))
     }
 
-    // Update the effects
-    fn update_effects(&mut self, ctx: &mut Context, dt: f32) -> GameResult {
-
-        // Ensure the quadtree is up-to-date
-        self.update_quadtree_if_needed(); 
-
-        // Counter to track the number of bubbles
-        let mut bubble_count = 0; 
-        let now = Instant::now();
-
-        // Get the screen 
-        let width = read_screen_width();
-        let height = read_screen_height();
-
-        // Collect damage requests, new effects, and sounds to play with Mutexes semaphores
-        let damage_requests = Mutex::new(Vec::new());
-        let new_effects = Mutex::new(Vec::new());
-        let sounds_to_play = Mutex::new(Vec::new());
-        let bubble_count = AtomicUsize::new(0);
-        let now = Instant::now();
-
-        // Update each effect
-        self.effects.par_iter_mut().for_each(|eff| {
-            let elapsed = now.duration_since(eff.started_at).as_secs_f32();
-            let speed = match eff.effect_type {
-                EffectType::Lightning => 200.0,
-                _ => 50.0,
-            };
-        
-            // Update the effect's position
-            let dx = speed * dt * eff.direction.cos();
-            let dy = speed * dt * eff.direction.sin();
-            eff.position.0 += dx;
-            eff.position.1 += dy;
-            
-            // Get screen dimensions
-            let width = read_screen_width();
-            let height = read_screen_height();
-            
-            // Check if the effect is outside the borders
-            let mut bounced = (eff.position.0 <= 0.0
-                || eff.position.0 >= width
-                || eff.position.1 <= 0.0
-                || eff.position.1 >= height);
-            
-            // Bounce the effect off the edges
-            eff.bounce(width, height);
-            
-            if bounced {
-                let boing_sounds = ["resources/sounds/boing/boing.ogg", "resources/sounds/boing/boing_casseur.ogg"];
-                let index = rand::rng().random_range(0..boing_sounds.len());
-                sounds_to_play.lock().unwrap().push(boing_sounds[index]);
+    // Apply an action of the user, recording it if needed
+    fn perform(&mut self, action: ReplayAction) {
+        if let Some((_, replay)) = &mut self.record {
+            replay.record(self.simulation.tick(), action.clone());
+        }
+
+        match action {
+            ReplayAction::Select { effect } => {
+                if let Some(id) = self.simulation.behaviors().id(&effect) {
+                    self.selected_effect = id;
+                }
             }
-            
-            // Process cells near the effect
-            let radius = read_cell_size();
-            let query_rect = ggez::graphics::Rect::new(
-                eff.position.0 - radius,
-                eff.position.1 - radius,
-                radius * 2.0,
-                radius * 2.0,
-            );
-            let candidates = self.terrain_quadtree.query(query_rect);
-            for candidate in candidates {
-                let cell_center_x = candidate.x;
-                let cell_center_y = candidate.y;
-                let distance = (eff.position.0 - cell_center_x).abs() + (eff.position.1 - cell_center_y).abs();
-                if distance <= radius {
-                    let dmg = match eff.effect_type {
-                        EffectType::Bubbles => 5.0,
-                        EffectType::MoreBubbles => 5.0,
-                        EffectType::Lightning => 10.0,
-                    };
-                    let ignore_durability = matches!(eff.effect_type, EffectType::Lightning);
-
-                    if self.terrain[candidate.tx][candidate.ty].material == Material::Air {
-                        continue;
+            ReplayAction::Spawn { x, y, effect } => {
+                if let Some(id) = self.simulation.behaviors().id(&effect) {
+                    self.simulation.spawn_effect(x, y, id);
+                }
+            }
+            ReplayAction::Pour { x, y, material, radius } => {
+                if let Some(id) = self.simulation.materials().id(&material) {
+                    self.simulation.pour(x, y, id, radius);
+                }
+            }
+            ReplayAction::Reset => {
+                // Go back to the imported terrain if any, unless the world was resized since
+                match self.base_terrain.clone().map(|terrain| self.simulation.set_terrain(terrain)) {
+                    Some(Ok(())) => {}
+                    Some(Err(e)) => {
+                        println!("Warning: Could not restore the imported terrain ({}). Using a generated terrain instead.", e);
+                        self.simulation.generate_terrain();
                     }
+                    None => self.simulation.generate_terrain(),
+                }
 
-                    damage_requests.lock().unwrap().push((candidate.tx, candidate.ty, dmg, ignore_durability));
-
-                    let cell = &self.terrain[candidate.tx][candidate.ty];
-                    if cell.material != Material::Air {
-                        let remaining = if ignore_durability { 0.0 } else { cell.durability - dmg };
-                        if remaining > 0.0 {
-                            eff.direction += std::f32::consts::PI 
-                                + rand::rng().random_range(-std::f32::consts::PI..std::f32::consts::PI);
-                            bounced = true;
-                            break; // Bounce off the first intact cell
-                        }
-                    }
+                // Clear all effects
+                self.simulation.clear_effects();
+            }
+            ReplayAction::SetNoise { noise } => {
+                self.config.noise_type = noise;
+                if let Err(e) = self.simulation.reconfigure(&self.config) {
+                    println!("Warning: Could not regenerate the terrain ({}).", e);
                 }
+                self.simulation.clear_effects();
+                println!("Terrain generated with {} noise.", noise);
             }
-            
-            // Spawn sub-effects if not already spawned
-            if !eff.spawned {
-                match eff.effect_type {
-                    EffectType::Bubbles => {
-                        if rand::rng().random_bool(0.2) && bubble_count.load(Ordering::SeqCst) < 10 {
-                            let offset = rand::rng().random_range(-0.3..0.3);
-                            let d1 = eff.direction + offset;
-                            let d2 = eff.direction - offset;
-                            {
-                                let mut ne = new_effects.lock().unwrap();
-                                ne.push(Effect {
-                                    effect_type: EffectType::Bubbles,
-                                    position: eff.position,
-                                    direction: d1,
-                                    started_at: Instant::now(),
-                                    spawned: true,
-                                });
-                                ne.push(Effect {
-                                    effect_type: EffectType::Bubbles,
-                                    position: eff.position,
-                                    direction: d2,
-                                    started_at: Instant::now(),
-                                    spawned: true,
-                                });
-                            }
-                            bubble_count.fetch_add(2, Ordering::SeqCst);
-                            eff.spawned = true;
-                        }
-                    }
-                    EffectType::MoreBubbles => {
-                        if rand::rng().random_bool(0.5)
-                            && bubble_count.load(Ordering::SeqCst) < 10
-                            && !eff.spawned
-                        {
-                            for _ in 0..10 {
-                                if bubble_count.load(Ordering::SeqCst) >= 10 {
-                                    break;
-                                }
-                                let offset = rand::rng().random_range(-0.5..0.5);
-                                new_effects.lock().unwrap().push(Effect {
-                                    effect_type: EffectType::MoreBubbles,
-                                    position: eff.position,
-                                    direction: eff.direction + offset,
-                                    started_at: Instant::now(),
-                                    spawned: true,
-                                });
-                                bubble_count.fetch_add(1, Ordering::SeqCst);
-                            }
-                            eff.spawned = true;
-                        }
-                    }
-                    _ => {}
+            ReplayAction::Resize { width, height } => {
+                self.config.terrain_width = width;
+                self.config.terrain_height = height;
+                if let Err(e) = self.simulation.reconfigure(&self.config) {
+                    println!("Warning: Could not resize the world ({}).", e);
+                }
+                self.camera.set_world_size(self.simulation.width(), self.simulation.height());
+            }
+            ReplayAction::SetCellSize { cell_size } => {
+                let ratio = cell_size / self.config.cell_size;
+                self.config.cell_size = cell_size;
+                if let Err(e) = self.simulation.reconfigure(&self.config) {
+                    println!("Warning: Could not resize the cells ({}).", e);
                 }
+                self.camera.scale_world(ratio);
+                println!("Cell size set to {}.", cell_size);
             }
-        });
+        }
+        self.process_simulation_events();
+    }
 
-        // Add new effects to the list
-        self.effects.extend(new_effects.lock().unwrap().drain(..));
-        
-        // Process all collected damage requests after the loop
-        for (tx, ty, dmg, ignore_durability) in damage_requests.lock().unwrap().drain(..) {
-            self.damage_terrain_at(tx, ty, dmg, ignore_durability);
+    // Lay out the UI buttons, wrapping them to new rows
+    fn buttons(&self) -> Vec<(String, ButtonAction, Rect)> {
+        let behaviors = self.simulation.behaviors();
+        let actions = [("Reset".to_string(), ButtonAction::Reset), ("Save".to_string(), ButtonAction::Save), ("Load".to_string(), ButtonAction::Load)]
+            .into_iter()
+            .chain(behaviors.ids().map(|id| (behaviors.get(id).name().to_string(), ButtonAction::Select(id))));
+
+        let per_row = (((self.config.screen_width - BUTTON_MARGIN) / (BUTTON_WIDTH + BUTTON_SPACING)) as usize).max(1);
+        actions.enumerate().map(|(i, (label, action))| {
+            let button_x = BUTTON_MARGIN + (i % per_row) as f32 * (BUTTON_WIDTH + BUTTON_SPACING);
+            let button_y = BUTTON_MARGIN + (i / per_row) as f32 * (BUTTON_HEIGHT + BUTTON_SPACING);
+            (label, action, Rect::new(button_x, button_y, BUTTON_WIDTH, BUTTON_HEIGHT))
+        }).collect()
+    }
+
+    // Save the terrain to a file
+    fn save_terrain(&self, path: &str) {
+        match self.simulation.snapshot().save(path) {
+            Ok(()) => println!("Terrain saved to {}.", path),
+            Err(e) => println!("Warning: Could not save the terrain ({}).", e),
         }
+    }
 
-        // Remove expired effects
-        self.effects.retain(|eff| now.duration_since(eff.started_at) < Duration::from_secs_f32(3.0));
+    // Restore the terrain from a file, unless the session is recorded
+    fn load_terrain(&mut self, path: &str) {
+        // The loaded terrain is not part of the recording, which could not be replayed
+        if self.record.is_some() {
+            println!("Warning: Could not load the terrain (the session is being recorded).");
+            return;
+        }
 
-        // Play all collected sounds after the loop
-        for sound_path in sounds_to_play.lock().unwrap().drain(..) {
-            self.play_sound(sound_path, 0.2);
+        match TerrainSnapshot::load(path).and_then(|snapshot| self.simulation.restore(&snapshot).map(|()| snapshot)) {
+            Ok(snapshot) => {
+                // The terrain is now generated from the saved seed, mode, noise and parameters
+                self.config.seed = snapshot.seed;
+                self.config.mode = snapshot.mode;
+                self.config.noise_type = snapshot.noise_type;
+                self.config.noise_params = snapshot.noise_params;
+                println!("Terrain loaded from {}.", path);
+            }
+            Err(e) => println!("Warning: Could not load the terrain ({}).", e),
         }
-    
-        Ok(())
+        self.process_simulation_events();
     }
 
     // Play a sound effect
@@ -576,48 +561,6 @@ impl MainState {
         // Store the sink to keep it alive
         self.sinks.push(Arc::new(sink));
     }
-
-    // Update the quadtree if needed
-    fn update_quadtree_if_needed(&mut self) {
-
-        // Check if the quadtree is dirty
-        if self.quadtree_dirty {
-
-            // Read the terrain dimensions and cell size
-            let terrain_width = read_terrain_width();
-            let terrain_height = read_terrain_height();
-            let cell_size = read_cell_size();
-            
-            // Rebuild the quadtree
-            let boundary = Rect::new(0.0, 0.0, terrain_width as f32 * cell_size, terrain_height as f32 * cell_size);
-
-            // Adjust capacity
-            self.terrain_quadtree = quadtree::QuadTree::new(boundary, 8);
-            
-            // Insert non-air cells into the quadtree
-            for tx in 0..terrain_width {
-                for ty in 0..terrain_height {
-
-                    // Current cell
-                    let cell = &self.terrain[tx][ty];
-
-                    // Insert non-air cells into the quadtree
-                    if cell.material != Material::Air {
-
-                        // Compute the center of cell
-                        let cx = tx as f32 * cell_size + cell_size / 2.0;
-                        let cy = ty as f32 * cell_size + cell_size / 2.0;
-
-                        // Insert the cell into the quadtree
-                        self.terrain_quadtree.insert(quadtree::QuadTreeItem { x: cx, y: cy, tx, ty });
-                    }
-                }
-            }
-
-            // Reset the dirty flag
-            self.quadtree_dirty = false;
-        }
-    }
 }
 
 // Implement EventHandler<ggez::GameError> properly for ggez
@@ -645,17 +588,73 @@ impl EventHandler<GameError> for MainState {
         // Rest of the update logic
         self.sinks.retain(|sink| !sink.empty());
 
-        let dt = read_delta(); // Fixed delta time
+        // Fade the explosion flashes
+        let frame_time = ctx.time.delta().as_secs_f32();
+        for blast in &mut self.blasts {
+            blast.3 -= frame_time;
+        }
+        self.blasts.retain(|blast| blast.3 > 0.0);
 
-        // Update the game state
-        self.update_effects(ctx, dt)
+        // Pan the camera with the WASD keys, the world moving the other way
+        let keys = [(KeyCode::A, 1.0, 0.0), (KeyCode::D, -1.0, 0.0), (KeyCode::W, 0.0, 1.0), (KeyCode::S, 0.0, -1.0)];
+        for (key, dx, dy) in keys {
+            if ctx.keyboard.is_key_pressed(key) {
+                self.camera.pan(dx * CAMERA_PAN_SPEED * frame_time, dy * CAMERA_PAN_SPEED * frame_time);
+            }
+        }
+        self.simulation.set_view(self.camera.view());
+
+        let dt = self.config.delta; // Fixed delta time
+
+        // Feed the recorded actions of this tick back into the simulation
+        if let Some(replay) = self.replay.take() {
+            let actions: Vec<ReplayAction> = replay.actions_at(self.simulation.tick()).cloned().collect();
+            for action in actions {
+                self.perform(action);
+            }
+            if self.simulation.tick() < replay.last_tick() {
+                self.replay = Some(replay);
+            } else {
+                println!("Replay finished.");
+            }
+        }
+
+        // Update the simulation
+        self.simulation.step(dt);
+        self.process_simulation_events();
+
+        Ok(())
     }
 
     // Implement the required event handler methods
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas = Canvas::from_frame(ctx, Color::WHITE);
 
-        self.instances.draw(&mut canvas, DrawParam::default());
+        // Draw the world through the camera
+        canvas.set_screen_coordinates(self.camera.view());
+
+        // Draw the terrain, chunk by chunk
+        self.update_cell_size(ctx)?;
+        self.update_chunk_instances(ctx);
+        for instances in self.chunk_instances.values() {
+            instances.draw(&mut canvas, DrawParam::default());
+        }
+
+        // Draw the falling debris
+        let cell_size = self.simulation.cell_size();
+        self.debris_instances.clear();
+        for debris in self.simulation.debris() {
+            for (dx, dy, cell) in &debris.cells {
+                let [r, g, b] = self.simulation.materials().get(cell.material).color;
+                self.debris_instances.push(DrawParam::default()
+                    .dest(ggez::mint::Point2 {
+                        x: (debris.x + dx) as f32 * cell_size,
+                        y: (debris.y + *dy as f32) * cell_size,
+                    })
+                    .color(Color::from_rgb(r, g, b)));
+            }
+        }
+        self.debris_instances.draw(&mut canvas, DrawParam::default());
 
         if self.show_intro {
             let mut canvas = Canvas::from_frame(ctx, Color::WHITE); // White background
@@ -675,8 +674,8 @@ impl EventHandler<GameError> for MainState {
             });
 
             let footer_dims = footer_text.dimensions(ctx).unwrap_or_default();
-            let footer_x = (self.screen_width - footer_dims.w) / 2.0;
-            let footer_y = self.screen_height - footer_dims.h - 10.0;
+            let footer_x = (self.config.screen_width - footer_dims.w) / 2.0;
+            let footer_y = self.config.screen_height - footer_dims.h - 10.0;
 
             canvas.draw(
                 &footer_text,
@@ -686,8 +685,8 @@ impl EventHandler<GameError> for MainState {
             );
         
             let text_dims = text.dimensions(ctx).unwrap_or_default();
-            let text_x = (self.screen_width - text_dims.w) / 2.0;
-            let text_y = (self.screen_height - text_dims.h) / 2.0;
+            let text_x = (self.config.screen_width - text_dims.w) / 2.0;
+            let text_y = (self.config.screen_height - text_dims.h) / 2.0;
         
             canvas.draw(
                 &text,
@@ -700,69 +699,49 @@ impl EventHandler<GameError> for MainState {
             return Ok(());
         }
 
-        // Use precomputed effect meshes for lightning, bubbles and more bubbles.
-        // Draw effects using the precomputed meshes.
-        for eff in &self.effects {
-            match eff.effect_type {
-                EffectType::Lightning => {
-                    canvas.draw(
-                        &self.lightning_mesh,
-                        DrawParam::default()
-                            .dest(ggez::mint::Point2 { x: eff.position.0, y: eff.position.1 })
-                            .rotation(eff.direction),
-                    );
-                }
-                EffectType::Bubbles => {
-                    canvas.draw(
-                        &self.bubble_mesh,
-                        DrawParam::default().dest(ggez::mint::Point2 { x: eff.position.0, y: eff.position.1 }),
-                    );
-                }
-                EffectType::MoreBubbles => {
-                    canvas.draw(
-                        &self.more_bubble_mesh,
-                        DrawParam::default().dest(ggez::mint::Point2 { x: eff.position.0, y: eff.position.1 }),
-                    );
-                }
+        // Draw effects using the precomputed mesh of their type
+        for eff in self.simulation.effects() {
+            let mut param = DrawParam::default().dest(ggez::mint::Point2 { x: eff.position.0, y: eff.position.1 });
+            if self.simulation.behaviors().get(eff.effect_type).visual().rotate {
+                param = param.rotation(eff.direction);
             }
+            canvas.draw(&self.effect_meshes[eff.effect_type.0 as usize], param);
         }
 
+        // Draw the explosion flashes, fading out
+        for &(x, y, radius, remaining) in &self.blasts {
+            let alpha = (remaining / BLAST_FLASH_DURATION * 160.0) as u8;
+            let flash = Mesh::new_circle(
+                ctx,
+                DrawMode::fill(),
+                ggez::mint::Point2 { x, y },
+                radius,
+                0.5,
+                Color::from_rgba(255, 200, 50, alpha),
+            )?;
+            canvas.draw(&flash, DrawParam::default());
+        }
+
+        // Draw the UI in screen coordinates
+        canvas.set_screen_coordinates(Rect::new(0.0, 0.0, self.config.screen_width, self.config.screen_height));
+
         // Draw buttons
-        let button_width = 100.0;
-        let button_height = 40.0;
-        let button_spacing = 10.0;
-        let button_y = 10.0;
-
-        // Buttons array now includes Reset
-        let buttons = [
-            ("Reset", None), // Reset button
-            ("Bubbles", Some(EffectType::Bubbles)),
-            ("MoreBubbles", Some(EffectType::MoreBubbles)),
-            ("Lightning", Some(EffectType::Lightning)),
-        ];
-
-        for (i, (label, effect_type)) in buttons.iter().enumerate() {
-            let button_x = 10.0 + i as f32 * (button_width + button_spacing);
-            let button_rect = Rect::new(button_x, button_y, button_width, button_height);
+        for (label, action, button_rect) in self.buttons() {
 
             // Change color if the button is selected
-            let button_color = if let Some(effect) = effect_type {
-                if *effect == self.selected_effect {
-                    Color::from_rgb(150, 150, 255) // Highlighted color
-                } else {
-                    Color::from_rgb(100, 100, 200) // Default color
-                }
+            let button_color = if action == ButtonAction::Select(self.selected_effect) {
+                Color::from_rgb(150, 150, 255) // Highlighted color
             } else {
-                Color::from_rgb(100, 100, 200) // Default color for Reset
+                Color::from_rgb(100, 100, 200) // Default color
             };
 
             let button_mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), button_rect, button_color)?;
             canvas.draw(&button_mesh, DrawParam::default());
 
-            let btn_label = Text::new(*label);
+            let btn_label = Text::new(label);
             let label_dims = btn_label.dimensions(ctx).unwrap_or_default();
-            let label_x = button_x + (button_width - label_dims.w) / 2.0;
-            let label_y = button_y + (button_height - label_dims.h) / 2.0;
+            let label_x = button_rect.x + (button_rect.w - label_dims.w) / 2.0;
+            let label_y = button_rect.y + (button_rect.h - label_dims.h) / 2.0;
             canvas.draw(
                 &btn_label,
                 DrawParam::default().dest(ggez::mint::Point2 { x: label_x, y: label_y }),
@@ -774,53 +753,178 @@ impl EventHandler<GameError> for MainState {
 
     // Handle mouse input
     fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) -> GameResult {
+        // The replayed session must not be disturbed
+        if self.replay.is_some() {
+            return Ok(());
+        }
+
+        // Effects and liquids go where the cursor points in the world
+        let (world_x, world_y) = self.camera.screen_to_world(x, y);
+        let in_world = (0.0..self.simulation.width()).contains(&world_x) && (0.0..self.simulation.height()).contains(&world_y);
+
         // Handle mouse button down events
         if button == MouseButton::Left {
-            let button_width = 100.0;
-            let button_height = 40.0;
-            let button_spacing = 10.0;
-            let button_y = 10.0;
-
-            // Buttons array
-            let buttons = [
-                (None, 0, "resources/sounds/btnclick.ogg"), // Reset button
-                (Some(EffectType::Bubbles), 1, "resources/sounds/btnclick.ogg"),
-                (Some(EffectType::MoreBubbles), 2, "resources/sounds/btnclick.ogg"),
-                (Some(EffectType::Lightning), 3, "resources/sounds/btnclick.ogg"),
-            ];
+            let buttons = self.buttons();
 
             // Check if the user clicked on a button
-            for (effect_type, i, sound_path) in buttons.iter() {
-
-                // Calculate the button rectangle
-                let button_x = 10.0 + *i as f32 * (button_width + button_spacing);
-                let button_rect = Rect::new(button_x, button_y, button_width, button_height);
+            for (_, action, button_rect) in &buttons {
 
                 // Check if the click is inside the button rectangle
                 if button_rect.contains(ggez::mint::Point2 { x, y }) {
                     // Play the button sound
-                    self.play_sound(sound_path, 0.2);
+                    self.play_sound("resources/sounds/btnclick.ogg", 0.2);
 
                     // Handle the button action
-                    if let Some(effect) = effect_type {
-                        // Update the selected effect
-                        self.selected_effect = *effect; 
-                    } else {
-                        // Reset button logic
-                        self.generate_terrain();
-                        
-                        // Clear all effects
-                        self.effects.clear(); 
+                    match *action {
+                        ButtonAction::Select(effect) => {
+                            // Update the selected effect
+                            let effect = self.simulation.behaviors().get(effect).name().to_string();
+                            self.perform(ReplayAction::Select { effect });
+                        }
+                        ButtonAction::Reset => self.perform(ReplayAction::Reset),
+                        ButtonAction::Save => self.save_terrain(QUICKSAVE_PATH),
+                        ButtonAction::Load => self.load_terrain(QUICKSAVE_PATH),
                     }
                     return Ok(());
                 }
             }
 
-            // If user clicked on the main canvas
-            if y > button_y + button_height {
-                self.spawn_effect(x, y);
+            // If user clicked on the world, below the buttons
+            let buttons_bottom = buttons.iter().map(|(_, _, rect)| rect.bottom()).fold(0.0, f32::max);
+            if y > buttons_bottom && in_world {
+                let effect = self.simulation.behaviors().get(self.selected_effect).name().to_string();
+                self.perform(ReplayAction::Spawn { x: world_x, y: world_y, effect });
+            }
+        } else if button == MouseButton::Right && in_world
+            && let Some(liquid) = self.selected_liquid
+        {
+            // Pour the selected liquid
+            let material = self.simulation.materials().get(liquid).name.clone();
+            self.perform(ReplayAction::Pour { x: world_x, y: world_y, material, radius: LIQUID_POUR_RADIUS });
+        }
+        Ok(())
+    }
+
+    // Pan the camera while dragging with the middle button
+    fn mouse_motion_event(&mut self, ctx: &mut Context, _x: f32, _y: f32, dx: f32, dy: f32) -> GameResult {
+        if ctx.mouse.button_pressed(MouseButton::Middle) {
+            self.camera.pan(dx, dy);
+        }
+        Ok(())
+    }
+
+    // Zoom the camera around the cursor
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: f32, y: f32) -> GameResult {
+        let cursor = ctx.mouse.position();
+        self.camera.zoom_at(cursor.x, cursor.y, ZOOM_STEP.powf(y));
+        Ok(())
+    }
+
+    // Handle keyboard shortcuts
+    fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult {
+        // Cycle through the noise types, backwards while holding shift
+        if input.keycode == Some(KeyCode::N) && !repeated && self.replay.is_none() {
+            let types = NoiseType::value_variants();
+            let current = types.iter().position(|&t| t == self.simulation.noise_type()).unwrap_or(0);
+            let next = if input.mods.contains(KeyMods::SHIFT) {
+                (current + types.len() - 1) % types.len()
+            } else {
+                (current + 1) % types.len()
+            };
+            self.perform(ReplayAction::SetNoise { noise: types[next] });
+        }
+
+        // Cycle through the liquid materials, backwards while holding shift
+        if input.keycode == Some(KeyCode::L) && !repeated {
+            let liquids = self.simulation.materials().liquids();
+            if let Some(current) = self.selected_liquid.and_then(|liquid| liquids.iter().position(|&l| l == liquid)) {
+                let next = if input.mods.contains(KeyMods::SHIFT) {
+                    (current + liquids.len() - 1) % liquids.len()
+                } else {
+                    (current + 1) % liquids.len()
+                };
+                self.selected_liquid = Some(liquids[next]);
+                println!("Pouring {}.", self.simulation.materials().get(liquids[next]).name);
+            }
+        }
+
+        // Grow the cells, or shrink them while holding shift, refitting the world to the window
+        if input.keycode == Some(KeyCode::C) && !repeated && self.replay.is_none() {
+            let step = if input.mods.contains(KeyMods::SHIFT) { -CELL_SIZE_STEP } else { CELL_SIZE_STEP };
+            let cell_size = (self.config.cell_size + step).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE.max(self.config.cell_size));
+            if cell_size != self.config.cell_size {
+                self.perform(ReplayAction::SetCellSize { cell_size });
+                self.fit_world_to_window();
+            }
+        }
+
+        if input.keycode == Some(KeyCode::F12) && !repeated {
+            // Shift exports the bare terrain of the whole world, one pixel per cell, otherwise the view is captured
+            let world = (0..self.simulation.terrain_width(), 0..self.simulation.terrain_height());
+            let (path, cells, scale, overlays) = if input.mods.contains(KeyMods::SHIFT) {
+                (format!("terrain-{}.png", self.simulation.tick()), world, 1, false)
+            } else {
+                let view = self.simulation.cells_in(&self.camera.view());
+                (format!("screenshot-{}.png", self.simulation.tick()), view, self.simulation.cell_size() as u32, true)
+            };
+            match export_terrain_image(&self.simulation, &path, cells, scale, overlays) {
+                Ok(()) => println!("Terrain exported to {}.", path),
+                Err(e) => println!("Warning: Could not export the terrain ({}).", e),
             }
         }
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Follow the new size of the window
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        self.config.screen_width = width;
+        self.config.screen_height = height;
+        self.camera.set_screen_size(width, height);
+
+        // Extend or shrink the world to the window, unless a replay dictates its size
+        self.fit_world_to_window();
+        self.simulation.set_view(self.camera.view());
+        Ok(())
+    }
+
+    // Save the terrain before quitting, if requested
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        if let Some(path) = &self.save_on_exit {
+            self.save_terrain(path);
+        }
+        if let Some((path, replay)) = &self.record {
+            match replay.save(path) {
+                Ok(()) => println!("Session recorded to {}.", path),
+                Err(e) => println!("Warning: Could not record the session ({}).", e),
+            }
+        }
+        Ok(false)
+    }
+}
+
+// Build the mesh of each effect type from its visual, sized in cells
+fn build_effect_meshes(ctx: &Context, behaviors: &EffectRegistry, cell_size: f32) -> GameResult<Vec<Mesh>> {
+    let mut effect_meshes = Vec::with_capacity(behaviors.len());
+    for id in behaviors.ids() {
+        let visual = behaviors.get(id).visual();
+        let [r, g, b] = visual.color;
+        let mesh = match visual.shape {
+            Shape::Circle { radius } => Mesh::new_circle(
+                ctx,
+                DrawMode::fill(),
+                ggez::mint::Point2 { x: 0.0, y: 0.0 },
+                radius * cell_size,
+                0.5,
+                Color::from_rgb(r, g, b),
+            )?,
+            Shape::Rectangle { width, height } => Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                Rect::new(-width * cell_size / 2.0, -height * cell_size / 2.0, width * cell_size, height * cell_size),
+                Color::from_rgb(r, g, b),
+            )?,
+        };
+        effect_meshes.push(mesh);
+    }
+    Ok(effect_meshes)
+}
@@ -1,24 +1,194 @@
-
 // -----------------------------------------------------------------------------
 // File: materials.rs
-// Description: Contains the Material enum used to represent different types of terrain materials.
+// Description: Contains the material registry used to represent different types of terrain materials.
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: March 15, 2025
-// Last modified: March 25, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
-/// Represents different types of terrain materials.
-/// 
-/// # Variants
-/// 
-/// - `Air`: Represents empty space or air.
-/// - `Grass`: Represents grassy terrain.
-/// - `Rock`: Represents rocky terrain.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Material {
-    Air,
-    Grass,
-    Rock,
+use serde::Deserialize;
+use std::fs;
+
+use crate::config::{Overrides, apply_overrides};
+
+// Built-in material definitions, used when no material file can be loaded
+const DEFAULT_MATERIALS: &str = include_str!("../resources/materials.toml");
+
+/// A compact identifier of a material, indexing into a `MaterialRegistry`.
+///
+/// The identifier `0` is always air, that is empty space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub u8);
+
+impl MaterialId {
+    /// Represents empty space or air.
+    pub const AIR: MaterialId = MaterialId(0);
+}
+
+/// The definition of a terrain material, as read from a material file.
+///
+/// # Fields
+///
+/// - `name`: The unique name of the material.
+/// - `durability`: The durability of a freshly generated cell of this material.
+/// - `color`: The RGB color used to draw the material.
+/// - `sound`: The sound played when a cell of this material breaks, if any.
+/// - `indestructible`: Whether cells of this material ignore all damage.
+/// - `liquid`: Whether the material is a liquid.
+/// - `granular`: Whether the material is loose and falls when unsupported (falling sand).
+/// - `dissolves`: The names of the materials turned into air on contact with this one (e.g. lava burning grass).
+#[derive(Debug, Clone, Deserialize)]
+pub struct MaterialDef {
+    pub name: String,
+    #[serde(default)]
+    pub durability: f32,
+    pub color: [u8; 3],
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(default)]
+    pub indestructible: bool,
+    #[serde(default)]
+    pub liquid: bool,
+    #[serde(default)]
+    pub granular: bool,
+    #[serde(default)]
+    pub dissolves: Vec<String>,
+}
+
+// Layout of a material file: a list of `[[material]]` tables
+#[derive(Deserialize)]
+struct MaterialFile {
+    material: Vec<MaterialDef>,
+}
+
+/// The set of materials known to the simulation, indexed by `MaterialId`.
+///
+/// # Fields
+///
+/// - `materials`: The material definitions, the first one being air.
+#[derive(Debug, Clone)]
+pub struct MaterialRegistry {
+    materials: Vec<MaterialDef>,
+}
+
+/// # Methods
+///
+/// - `from_file(path: &str, overrides: &Overrides) -> Result<Self, String>`:
+///   Loads a registry from a TOML material file, with fields of its materials replaced.
+/// - `builtin(overrides: &Overrides) -> Result<Self, String>`:
+///   Builds the registry from the built-in material definitions, with fields of its materials replaced.
+/// - `parse(source: &str) -> Result<Self, String>`:
+///   Parses a registry from the contents of a TOML material file.
+/// - `get(id: MaterialId) -> &MaterialDef`:
+///   Returns the definition of a material.
+/// - `id(name: &str) -> Option<MaterialId>`:
+///   Looks up a material by name.
+/// - `len() -> usize`:
+///   Returns the number of materials.
+/// - `liquids() -> Vec<MaterialId>`:
+///   Returns the liquid materials, in registry order.
+impl MaterialRegistry {
+    pub fn from_file(path: &str, overrides: &Overrides) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        apply_overrides(&source, "material", overrides).and_then(|source| Self::parse(&source)).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn builtin(overrides: &Overrides) -> Result<Self, String> {
+        apply_overrides(DEFAULT_MATERIALS, "material", overrides).and_then(|source| Self::parse(&source))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let file: MaterialFile = toml::from_str(source).map_err(|e| e.to_string())?;
+        let materials = file.material;
+
+        // Air must come first so that `MaterialId::AIR` is always valid
+        if materials.first().map(|m| m.name.as_str()) != Some("air") {
+            return Err("the first material must be \"air\"".to_string());
+        }
+
+        // Identifiers are stored on a single byte
+        if materials.len() > u8::MAX as usize + 1 {
+            return Err(format!("too many materials ({}, at most 256)", materials.len()));
+        }
+
+        // Names must be unique to be looked up
+        for (i, material) in materials.iter().enumerate() {
+            if materials[..i].iter().any(|m| m.name == material.name) {
+                return Err(format!("duplicate material \"{}\"", material.name));
+            }
+        }
+
+        // Dissolved materials must exist, so that a misspelt name does not go unnoticed
+        for material in &materials {
+            if let Some(name) = material.dissolves.iter().find(|&name| !materials.iter().any(|m| &m.name == name)) {
+                return Err(format!("material \"{}\" dissolves unknown material \"{}\"", material.name, name));
+            }
+        }
+
+        Ok(Self { materials })
+    }
+
+    pub fn get(&self, id: MaterialId) -> &MaterialDef {
+        &self.materials[id.0 as usize]
+    }
+
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.materials.iter().position(|m| m.name == name).map(|i| MaterialId(i as u8))
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn liquids(&self) -> Vec<MaterialId> {
+        (0..self.materials.len()).map(|i| MaterialId(i as u8)).filter(|&id| self.get(id).liquid).collect()
+    }
+}
+
+/// Builds the registry from the built-in material definitions.
+impl Default for MaterialRegistry {
+    fn default() -> Self {
+        Self::parse(DEFAULT_MATERIALS).expect("Invalid built-in material definitions")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parse a material file expected to be rejected, returning the error
+    fn error(source: &str) -> String {
+        MaterialRegistry::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn builtin_materials_parse() {
+        let materials = MaterialRegistry::default();
+        assert_eq!(materials.id("air"), Some(MaterialId::AIR));
+        assert!(materials.liquids().contains(&materials.id("water").unwrap()));
+    }
+
+    #[test]
+    fn air_comes_first() {
+        assert!(error("[[material]]\nname = \"rock\"\ncolor = [0, 0, 0]\n").contains("first material"));
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let source = "[[material]]\nname = \"air\"\ncolor = [0, 0, 0]\n\n[[material]]\nname = \"air\"\ncolor = [1, 1, 1]\n";
+        assert_eq!(error(source), "duplicate material \"air\"");
+    }
+
+    #[test]
+    fn rejects_unknown_dissolved_materials() {
+        let source = "[[material]]\nname = \"air\"\ncolor = [0, 0, 0]\n\n[[material]]\nname = \"lava\"\ncolor = [1, 1, 1]\ndissolves = [\"gras\"]\n";
+        assert_eq!(error(source), "material \"lava\" dissolves unknown material \"gras\"");
+    }
+
+    #[test]
+    fn rejects_missing_fields() {
+        assert!(MaterialRegistry::parse("[[material]]\nname = \"air\"\n").is_err());
+    }
 }
@@ -1,68 +1,150 @@
 // -----------------------------------------------------------------------------
 // File: noisegenerator.rs
-// Description: This module contains the `NoiseGenerator` trait and its implementations for different noise types.
+// Description: This module contains the `NoiseGenerator` struct, sampling the noise algorithm of the current
+//              noise type with its parameters.
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: April 05, 2025
-// Last modified: April 05, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
-use noise::{NoiseFn, Perlin, Fbm, Simplex, Seedable};
+use noise::{Billow, Fbm, MultiFractal, NoiseFn, OpenSimplex, Perlin, RidgedMulti, Simplex, Value, Vector2};
+use noise::core::worley::{ReturnType, distance_functions, worley_2d};
+use noise::permutationtable::PermutationTable;
+use serde::{Deserialize, Serialize};
 use crate::noisetypes::NoiseType;
 
-/// Represents a collection of noise generation techniques used for procedural terrain generation.
+/// The default frequency of the noise, per cell.
+pub const DEFAULT_FREQUENCY: f64 = 0.05;
+
+/// The maximum number of octaves of the fractal noises.
+pub const MAX_OCTAVES: usize = 32;
+
+/// Represents the parameters shaping the noise.
+///
+/// The octaves, lacunarity and persistence only apply to the fractal noises (fbm, ridged multifractal
+/// and billow); when unset, each algorithm keeps its own default.
+///
+/// # Fields
+/// - `frequency`: The frequency of the noise, per cell. A lower frequency gives larger features.
+/// - `octaves`: The number of layers of noise added together.
+/// - `lacunarity`: The frequency multiplier between successive octaves.
+/// - `persistence`: The amplitude multiplier between successive octaves.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseParams {
+    pub frequency: f64,
+    pub octaves: Option<usize>,
+    pub lacunarity: Option<f64>,
+    pub persistence: Option<f64>,
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self { frequency: DEFAULT_FREQUENCY, octaves: None, lacunarity: None, persistence: None }
+    }
+}
+
+// Worley noise giving the distance to the nearest feature point. Unlike `noise::Worley`, which
+// keeps its distance function behind an `Rc`, it can be shared between threads. Like every noise, it is
+// sampled at the frequency of the parameters, but it has no octaves, lacunarity nor persistence.
+struct Cellular {
+    perm_table: PermutationTable,
+}
+
+impl NoiseFn<f64, 2> for Cellular {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        worley_2d(&self.perm_table, distance_functions::euclidean, ReturnType::Distance, Vector2::from(point))
+    }
+}
+
+/// Represents the noise used for procedural terrain generation.
 ///
-/// This struct aggregates multiple noise generator implementations:
+/// Only the algorithm of the current type is instantiated, and it is rebuilt whenever
+/// the seed or the type changes. The noise can be sampled from several threads at once:
 /// - seed: The seed used for generating noise.
-/// - fbm: An instance providing fractal brownian motion noise.
-/// - simplex: An instance providing simplex noise.
-/// - perlin: An instance providing Perlin noise.
+/// - params: The parameters shaping the noise.
+/// - source: The instance of the current noise algorithm.
 /// - current_type: Tracks which type of noise is currently active.
-///
-/// These generators can be switched or combined to achieve a variety of noise effects.
 pub struct NoiseGenerator {
     seed: u32,
-    fbm: Fbm<Perlin>,
-    simplex: Simplex,
-    perlin: Perlin,
+    params: NoiseParams,
+    source: Box<dyn NoiseFn<f64, 2> + Send + Sync>,
     current_type: NoiseType,
 }
 
 impl NoiseGenerator {
-    /// Creates a new `NoiseGenerator` instance with the specified noise type.
-    pub fn new(noise_type: NoiseType, seed: u32) -> Self {
+    /// Creates a new `NoiseGenerator` instance with the specified noise type and parameters.
+    pub fn new(noise_type: NoiseType, seed: u32, params: NoiseParams) -> Self {
         Self {
             seed,
-            fbm: Fbm::new(seed),
-            simplex: Simplex::new(seed),
-            perlin: Perlin::new(seed),
+            params,
+            source: Self::build(noise_type, seed, &params),
             current_type: noise_type,
         }
     }
 
+    // Instantiate the algorithm of a noise type
+    fn build(noise_type: NoiseType, seed: u32, params: &NoiseParams) -> Box<dyn NoiseFn<f64, 2> + Send + Sync> {
+        match noise_type {
+            NoiseType::Perlin => Box::new(Perlin::new(seed)),
+            NoiseType::Fbm => Box::new(Self::fractal(Fbm::<Perlin>::new(seed), params)),
+            NoiseType::Simplex => Box::new(Simplex::new(seed)),
+            // The distance to the nearest feature point carves round pockets
+            NoiseType::Worley => Box::new(Cellular { perm_table: PermutationTable::new(seed) }),
+            NoiseType::RidgedMulti => Box::new(Self::fractal(RidgedMulti::<Perlin>::new(seed), params)),
+            NoiseType::Billow => Box::new(Self::fractal(Billow::<Perlin>::new(seed), params)),
+            NoiseType::Value => Box::new(Value::new(seed)),
+            NoiseType::OpenSimplex => Box::new(OpenSimplex::new(seed)),
+        }
+    }
+
+    // Apply the parameters that are set to a fractal noise
+    fn fractal<T: MultiFractal>(mut noise: T, params: &NoiseParams) -> T {
+        if let Some(octaves) = params.octaves {
+            noise = noise.set_octaves(octaves);
+        }
+        if let Some(lacunarity) = params.lacunarity {
+            noise = noise.set_lacunarity(lacunarity);
+        }
+        if let Some(persistence) = params.persistence {
+            noise = noise.set_persistence(persistence);
+        }
+        noise
+    }
+
     /// Regenerates the current noise generator with a new seed.
     pub fn generate(&mut self, seed: u32) {
         self.seed = seed;
-        match self.current_type {
-            NoiseType::Fbm => self.fbm = Fbm::new(self.seed).set_seed(seed),
-            NoiseType::Perlin => self.perlin = Perlin::new(self.seed).set_seed(seed),
-            NoiseType::Simplex => self.simplex = Simplex::new(self.seed).set_seed(seed),
-        }
+        self.source = Self::build(self.current_type, seed, &self.params);
     }
 
-    /// Gets the noise value for the given `[x, y]` coordinates from the current noise type.
+    /// Gets the noise value for the given `[x, y]` cell coordinates from the current noise type,
+    /// scaled by the frequency.
     pub fn get(&self, x: f64, y: f64) -> f64 {
-        match self.current_type {
-            NoiseType::Fbm => self.fbm.get([x, y]),
-            NoiseType::Perlin => self.perlin.get([x, y]),
-            NoiseType::Simplex => self.simplex.get([x, y]),
-        }
+        self.source.get([x * self.params.frequency, y * self.params.frequency])
+    }
+
+    /// Returns the current noise type.
+    pub fn noise_type(&self) -> NoiseType {
+        self.current_type
     }
 
     /// Sets the current noise type.
     pub fn set_noise_type(&mut self, noise_type: NoiseType) {
         self.current_type = noise_type;
+        self.source = Self::build(noise_type, self.seed, &self.params);
     }
-}
 
+    /// Returns the parameters shaping the noise.
+    pub fn params(&self) -> NoiseParams {
+        self.params
+    }
+
+    /// Sets the parameters shaping the noise.
+    pub fn set_params(&mut self, params: NoiseParams) {
+        self.params = params;
+        self.source = Self::build(self.current_type, self.seed, &params);
+    }
+}
@@ -0,0 +1,377 @@
+// -----------------------------------------------------------------------------
+// File: noisegraph.rs
+// Description: A graph of noise modules combined together (add, multiply,
+//              select, domain warp, clamp, vertical gradient), read from a
+//              graph file and evaluated to generate the terrain.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use serde::Deserialize;
+use std::fs;
+
+use crate::noisegenerator::{MAX_OCTAVES, NoiseGenerator, NoiseParams};
+use crate::noisetypes::NoiseType;
+
+/// A module of a noise graph, as read from a graph file.
+///
+/// Sources are referred to by the names of nodes defined earlier in the file.
+///
+/// # Variants
+///
+/// - `Noise`: A noise, of the current type unless `noise` is given. Its seed is the terrain seed plus
+///   `seed`, and the parameters that are not given are the current ones.
+/// - `Constant`: The same value everywhere.
+/// - `Gradient`: A vertical gradient going from -1 at the `top` to 1 at the `bottom` of the terrain,
+///   both given as fractions of its height.
+/// - `Add`: The sum of the sources.
+/// - `Multiply`: The product of the sources.
+/// - `Clamp`: The source, clamped between `min` and `max`.
+/// - `Select`: The `low` source where the `control` source is below the `threshold`, the `high` source
+///   elsewhere, blended over `falloff` on either side of the threshold.
+/// - `Warp`: The source, evaluated at coordinates moved by the `x` and `y` sources times `strength`, in cells.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NodeKind {
+    Noise {
+        #[serde(default)]
+        noise: Option<NoiseType>,
+        #[serde(default)]
+        seed: u32,
+        #[serde(default)]
+        frequency: Option<f64>,
+        #[serde(default)]
+        octaves: Option<usize>,
+        #[serde(default)]
+        lacunarity: Option<f64>,
+        #[serde(default)]
+        persistence: Option<f64>,
+    },
+    Constant {
+        value: f64,
+    },
+    Gradient {
+        #[serde(default)]
+        top: f64,
+        #[serde(default = "default_bottom")]
+        bottom: f64,
+    },
+    Add {
+        sources: Vec<String>,
+    },
+    Multiply {
+        sources: Vec<String>,
+    },
+    Clamp {
+        source: String,
+        #[serde(default = "default_min")]
+        min: f64,
+        #[serde(default = "default_max")]
+        max: f64,
+    },
+    Select {
+        control: String,
+        low: String,
+        high: String,
+        #[serde(default)]
+        threshold: f64,
+        #[serde(default)]
+        falloff: f64,
+    },
+    Warp {
+        source: String,
+        #[serde(default)]
+        x: Option<String>,
+        #[serde(default)]
+        y: Option<String>,
+        strength: f64,
+    },
+}
+
+fn default_bottom() -> f64 {
+    1.0
+}
+
+fn default_min() -> f64 {
+    -1.0
+}
+
+fn default_max() -> f64 {
+    1.0
+}
+
+/// A named node of a noise graph, as read from a graph file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeDef {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+}
+
+// Layout of a graph file: a list of `[[node]]` tables and the name of the output node
+#[derive(Deserialize)]
+struct GraphFile {
+    #[serde(default)]
+    output: Option<String>,
+    node: Vec<NodeDef>,
+}
+
+// A node with its sources resolved to indices
+enum Node {
+    Noise { def: NodeKind, generator: NoiseGenerator },
+    Constant(f64),
+    Gradient { top: f64, bottom: f64 },
+    Add(Vec<usize>),
+    Multiply(Vec<usize>),
+    Clamp { source: usize, min: f64, max: f64 },
+    Select { control: usize, low: usize, high: usize, threshold: f64, falloff: f64 },
+    Warp { source: usize, x: Option<usize>, y: Option<usize>, strength: f64 },
+}
+
+/// A graph of noise modules, giving the noise value of each cell of the terrain.
+///
+/// # Fields
+///
+/// - `nodes`: The nodes, each one only using nodes before it as sources.
+/// - `output`: The index of the node giving the noise value.
+pub struct NoiseGraph {
+    nodes: Vec<Node>,
+    output: usize,
+}
+
+/// # Methods
+///
+/// - `from_file(path: &str) -> Result<Self, String>`:
+///   Loads a graph from a TOML graph file.
+/// - `parse(source: &str) -> Result<Self, String>`:
+///   Parses a graph from the contents of a TOML graph file.
+/// - `generate(seed: u32, noise_type: NoiseType, params: NoiseParams)`:
+///   Regenerates the noises of the graph from the terrain seed and the current noise.
+/// - `get(x: f64, y: f64, height: f64) -> f64`:
+///   Evaluates the graph at the given cell coordinates, for a terrain of the given height.
+impl NoiseGraph {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::parse(&source).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let file: GraphFile = toml::from_str(source).map_err(|e| e.to_string())?;
+        if file.node.is_empty() {
+            return Err("at least one node is required".to_string());
+        }
+
+        let mut nodes = Vec::with_capacity(file.node.len());
+        for (i, def) in file.node.iter().enumerate() {
+            if file.node[..i].iter().any(|node| node.name == def.name) {
+                return Err(format!("duplicate node \"{}\"", def.name));
+            }
+
+            // Only earlier nodes can be sources, which rules out cycles
+            let index = |name: &str| {
+                file.node[..i].iter().position(|node| node.name == name)
+                    .ok_or(format!("node \"{}\" uses \"{}\", which is not defined before it", def.name, name))
+            };
+
+            let node = match &def.kind {
+                &NodeKind::Noise { frequency, octaves, lacunarity, persistence, .. } => {
+                    // Same ranges as the command-line flags
+                    let invalid = |setting: &str, range: &str| format!("node {} (\"{}\"): {} must be {}", i + 1, def.name, setting, range);
+                    if frequency.is_some_and(|frequency| !(frequency > 0.0 && frequency.is_finite())) {
+                        return Err(invalid("frequency", "positive"));
+                    }
+                    if octaves.is_some_and(|octaves| !(1..=MAX_OCTAVES).contains(&octaves)) {
+                        return Err(invalid("octaves", &format!("between 1 and {}", MAX_OCTAVES)));
+                    }
+                    if lacunarity.is_some_and(|lacunarity| !(lacunarity >= 1.0 && lacunarity.is_finite())) {
+                        return Err(invalid("lacunarity", "at least 1"));
+                    }
+                    if persistence.is_some_and(|persistence| !(persistence > 0.0 && persistence <= 1.0)) {
+                        return Err(invalid("persistence", "above 0 and at most 1"));
+                    }
+                    Node::Noise {
+                        def: def.kind.clone(),
+                        generator: NoiseGenerator::new(NoiseType::Perlin, 0, NoiseParams::default()),
+                    }
+                }
+                &NodeKind::Constant { value } => {
+                    if !value.is_finite() {
+                        return Err(format!("node \"{}\" must have a finite value", def.name));
+                    }
+                    Node::Constant(value)
+                }
+                &NodeKind::Gradient { top, bottom } => {
+                    if !top.is_finite() || !bottom.is_finite() {
+                        return Err(format!("node \"{}\" must have a finite top and bottom", def.name));
+                    }
+                    if bottom <= top {
+                        return Err(format!("node \"{}\" must have its bottom below its top", def.name));
+                    }
+                    Node::Gradient { top, bottom }
+                }
+                NodeKind::Add { sources } => Node::Add(sources.iter().map(|name| index(name)).collect::<Result<_, _>>()?),
+                NodeKind::Multiply { sources } => Node::Multiply(sources.iter().map(|name| index(name)).collect::<Result<_, _>>()?),
+                NodeKind::Clamp { source, min, max } => {
+                    // NaN bounds would make the clamp panic
+                    if !min.is_finite() || !max.is_finite() {
+                        return Err(format!("node \"{}\" must have a finite min and max", def.name));
+                    }
+                    if min > max {
+                        return Err(format!("node \"{}\" must have its min below its max", def.name));
+                    }
+                    Node::Clamp { source: index(source)?, min: *min, max: *max }
+                }
+                NodeKind::Select { control, low, high, threshold, falloff } => {
+                    if !threshold.is_finite() {
+                        return Err(format!("node \"{}\" must have a finite threshold", def.name));
+                    }
+                    if !(falloff.is_finite() && *falloff >= 0.0) {
+                        return Err(format!("node \"{}\" must have a finite, non-negative falloff", def.name));
+                    }
+                    Node::Select {
+                        control: index(control)?,
+                        low: index(low)?,
+                        high: index(high)?,
+                        threshold: *threshold,
+                        falloff: *falloff,
+                    }
+                }
+                NodeKind::Warp { source, x, y, strength } => {
+                    if !strength.is_finite() {
+                        return Err(format!("node \"{}\" must have a finite strength", def.name));
+                    }
+                    Node::Warp {
+                        source: index(source)?,
+                        x: x.as_deref().map(index).transpose()?,
+                        y: y.as_deref().map(index).transpose()?,
+                        strength: *strength,
+                    }
+                }
+            };
+            nodes.push(node);
+        }
+
+        // The last node is the output unless another one is named
+        let output = match &file.output {
+            Some(name) => file.node.iter().position(|node| &node.name == name).ok_or(format!("unknown output node \"{}\"", name))?,
+            None => nodes.len() - 1,
+        };
+
+        Ok(Self { nodes, output })
+    }
+
+    pub fn generate(&mut self, seed: u32, noise_type: NoiseType, params: NoiseParams) {
+        for node in &mut self.nodes {
+            if let Node::Noise { def: NodeKind::Noise { noise, seed: offset, frequency, octaves, lacunarity, persistence }, generator } = node {
+                let params = NoiseParams {
+                    frequency: frequency.unwrap_or(params.frequency),
+                    octaves: octaves.or(params.octaves),
+                    lacunarity: lacunarity.or(params.lacunarity),
+                    persistence: persistence.or(params.persistence),
+                };
+                *generator = NoiseGenerator::new(noise.unwrap_or(noise_type), seed.wrapping_add(*offset), params);
+            }
+        }
+    }
+
+    pub fn get(&self, x: f64, y: f64, height: f64) -> f64 {
+        self.eval(self.output, x, y, height)
+    }
+
+    // Evaluate a node at the given cell coordinates
+    fn eval(&self, index: usize, x: f64, y: f64, height: f64) -> f64 {
+        match &self.nodes[index] {
+            Node::Noise { generator, .. } => generator.get(x, y),
+            Node::Constant(value) => *value,
+            Node::Gradient { top, bottom } => {
+                let t = (y / height - top) / (bottom - top);
+                (t * 2.0 - 1.0).clamp(-1.0, 1.0)
+            }
+            Node::Add(sources) => sources.iter().map(|&source| self.eval(source, x, y, height)).sum(),
+            Node::Multiply(sources) => sources.iter().map(|&source| self.eval(source, x, y, height)).product(),
+            Node::Clamp { source, min, max } => self.eval(*source, x, y, height).clamp(*min, *max),
+            Node::Select { control, low, high, threshold, falloff } => {
+                let control = self.eval(*control, x, y, height);
+                if control < threshold - falloff {
+                    self.eval(*low, x, y, height)
+                } else if control >= threshold + falloff {
+                    self.eval(*high, x, y, height)
+                } else {
+                    // Blend linearly across the falloff
+                    let t = (control - (threshold - falloff)) / (2.0 * falloff);
+                    let (low, high) = (self.eval(*low, x, y, height), self.eval(*high, x, y, height));
+                    low + (high - low) * t
+                }
+            }
+            Node::Warp { source, x: dx, y: dy, strength } => {
+                let dx = dx.map_or(0.0, |dx| self.eval(dx, x, y, height));
+                let dy = dy.map_or(0.0, |dy| self.eval(dy, x, y, height));
+                self.eval(*source, x + dx * strength, y + dy * strength, height)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parse a graph file expected to be rejected, returning the error
+    fn error(source: &str) -> String {
+        NoiseGraph::parse(source).err().expect("the graph should be rejected")
+    }
+
+    // A graph file whose last node has the given fields, after a constant node named "a"
+    fn graph(node: &str) -> String {
+        format!("[[node]]\nname = \"a\"\ntype = \"constant\"\nvalue = 0.5\n\n[[node]]\nname = \"b\"\n{}\n", node)
+    }
+
+    #[test]
+    fn evaluates_nodes() {
+        let clamp = NoiseGraph::parse(&graph("type = \"clamp\"\nsource = \"a\"\nmin = 0.0\nmax = 0.25")).unwrap();
+        assert_eq!(clamp.get(3.0, 4.0, 10.0), 0.25);
+
+        let gradient = NoiseGraph::parse(&graph("type = \"gradient\"")).unwrap();
+        assert_eq!(gradient.get(0.0, 0.0, 10.0), -1.0);
+        assert_eq!(gradient.get(0.0, 5.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn builtin_graph_parses() {
+        let source = include_str!("../resources/noisegraph.toml");
+        let mut graph = NoiseGraph::parse(source).unwrap();
+        graph.generate(1, NoiseType::Perlin, NoiseParams::default());
+        assert!(graph.get(10.0, 10.0, 100.0).is_finite());
+    }
+
+    #[test]
+    fn rejects_invalid_structure() {
+        assert!(error("node = []\n").contains("at least one node"));
+        assert_eq!(error(&graph("type = \"constant\"\nvalue = 1.0").replace("\"b\"", "\"a\"")), "duplicate node \"a\"");
+        assert!(error(&graph("type = \"add\"\nsources = [\"a\", \"c\"]")).contains("\"c\", which is not defined before it"));
+        assert!(error(&graph("type = \"add\"\nsources = [\"b\"]")).contains("not defined before it"));
+        assert_eq!(error(&format!("output = \"c\"\n{}", graph("type = \"constant\"\nvalue = 1.0"))), "unknown output node \"c\"");
+    }
+
+    #[test]
+    fn rejects_invalid_noise_settings() {
+        assert!(error(&graph("type = \"noise\"\nfrequency = 0.0")).contains("frequency must be positive"));
+        assert!(error(&graph("type = \"noise\"\noctaves = 0")).contains("octaves"));
+        assert!(error(&graph("type = \"noise\"\nlacunarity = 0.5")).contains("lacunarity"));
+        assert!(error(&graph("type = \"noise\"\npersistence = nan")).contains("persistence"));
+    }
+
+    #[test]
+    fn rejects_non_finite_values() {
+        assert!(error(&graph("type = \"constant\"\nvalue = nan")).contains("finite value"));
+        assert!(error(&graph("type = \"gradient\"\ntop = 0.5\nbottom = 0.5")).contains("bottom below its top"));
+        assert!(error(&graph("type = \"clamp\"\nsource = \"a\"\nmin = nan")).contains("finite min and max"));
+        assert!(error(&graph("type = \"clamp\"\nsource = \"a\"\nmin = 1.0\nmax = 0.0")).contains("min below its max"));
+        assert!(error(&graph("type = \"select\"\ncontrol = \"a\"\nlow = \"a\"\nhigh = \"a\"\nthreshold = nan")).contains("finite threshold"));
+        assert!(error(&graph("type = \"select\"\ncontrol = \"a\"\nlow = \"a\"\nhigh = \"a\"\nfalloff = -0.1")).contains("non-negative falloff"));
+        assert!(error(&graph("type = \"warp\"\nsource = \"a\"\nstrength = inf")).contains("finite strength"));
+    }
+}
@@ -1,15 +1,16 @@
 // -----------------------------------------------------------------------------
 // File: noisetypes.rs
-// Description:
+// Description: The noise algorithms the terrain can be generated with.
 // Author(s): DIARRA Amara & SERRANO Jean-Léo
 // License: CC BY-NC 4.0
 // Created: March 26, 2025
-// Last modified: March 26, 2025
-// Version: 1.0
+// Last modified: October 16, 2026
+// Version: 1.1
 // -----------------------------------------------------------------------------
 
 use std::fmt;
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 
 /// Represents different types of noise materials.
 /// 
@@ -18,16 +19,45 @@ use clap::ValueEnum;
 /// - `Perlin`: Represents Perlin noise.
 /// - `Fbm`: Represents Fractal Brownian Motion (FBM) noise.
 /// - `Simplex`: Represents Simplex noise.
-#[derive(ValueEnum, Clone, Copy, PartialEq)]
+/// - `Worley`: Represents Worley (cellular) noise, giving cave-like pockets. Only the frequency applies to it:
+///   the octaves, lacunarity and persistence are ignored.
+/// - `RidgedMulti`: Represents ridged multifractal noise, giving sharp ridges and cliffs.
+/// - `Billow`: Represents billow noise, giving rounded, puffy shapes.
+/// - `Value`: Represents value noise.
+/// - `OpenSimplex`: Represents OpenSimplex noise.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum NoiseType {
     #[value(alias = "perlin")]
+    #[serde(alias = "perlin")]
     Perlin,
     
     #[value(alias = "fbm")]
+    #[serde(alias = "fbm")]
     Fbm,
 
     #[value(alias = "simplex")]
+    #[serde(alias = "simplex")]
     Simplex,
+
+    #[value(alias = "worley", alias = "cellular")]
+    #[serde(alias = "worley")]
+    Worley,
+
+    #[value(alias = "ridged-multi", alias = "ridged")]
+    #[serde(alias = "ridged-multi")]
+    RidgedMulti,
+
+    #[value(alias = "billow")]
+    #[serde(alias = "billow")]
+    Billow,
+
+    #[value(alias = "value")]
+    #[serde(alias = "value")]
+    Value,
+
+    #[value(alias = "open-simplex", alias = "opensimplex")]
+    #[serde(alias = "open-simplex")]
+    OpenSimplex,
 }
 
 impl fmt::Display for NoiseType {
@@ -38,6 +68,11 @@ impl fmt::Display for NoiseType {
             NoiseType::Perlin => write!(f, "Perlin"),
             NoiseType::Fbm => write!(f, "Fractal Brownian Motion"),
             NoiseType::Simplex => write!(f, "Simplex"),
+            NoiseType::Worley => write!(f, "Worley"),
+            NoiseType::RidgedMulti => write!(f, "Ridged Multifractal"),
+            NoiseType::Billow => write!(f, "Billow"),
+            NoiseType::Value => write!(f, "Value"),
+            NoiseType::OpenSimplex => write!(f, "OpenSimplex"),
         }
     }
 }
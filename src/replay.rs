@@ -0,0 +1,278 @@
+// -----------------------------------------------------------------------------
+// File: replay.rs
+// Description: Recording of the user's actions with their simulation tick, and
+//              their replay into the simulation.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::generationmodes::GenerationMode;
+use crate::noisegenerator::NoiseParams;
+use crate::noisetypes::NoiseType;
+
+/// The version of the replay format written by this build.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// An action of the user that changes the simulation.
+///
+/// Effects and materials are referred to by name, so that replays stay readable.
+///
+/// # Variants
+///
+/// - `Select`: An effect was selected with its button.
+/// - `Spawn`: An effect was spawned at `(x, y)`.
+/// - `Pour`: A material was poured at `(x, y)` within a radius, in cells.
+/// - `Reset`: The terrain was reset and the effects cleared.
+/// - `SetNoise`: The terrain was regenerated with another noise.
+/// - `Resize`: The world was resized to `width` by `height` cells, following the window.
+/// - `SetCellSize`: The cells were resized to `cell_size` world units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplayAction {
+    Select { effect: String },
+    Spawn { x: f32, y: f32, effect: String },
+    Pour { x: f32, y: f32, material: String, radius: usize },
+    Reset,
+    SetNoise { noise: NoiseType },
+    Resize { width: usize, height: usize },
+    SetCellSize { cell_size: f32 },
+}
+
+/// An action, with the tick of the simulation at which it happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    pub tick: u64,
+    pub action: ReplayAction,
+}
+
+/// A recorded session: the settings the simulation was started with and every action of the user.
+///
+/// Together with the deterministic random stream, replaying the actions at the same ticks
+/// reproduces the session exactly. The material, effect, layer and noise graph files, and an imported or loaded
+/// terrain, are not recorded and must be given again.
+///
+/// # Fields
+/// - `version`: The version of the replay format.
+/// - `terrain_width`: The width of the terrain, in cells.
+/// - `terrain_height`: The height of the terrain, in cells.
+/// - `cell_size`: The size of a cell, in world units (pixels).
+/// - `delta`: The fixed time step of the simulation, in seconds.
+/// - `seed`: The seed given for terrain generation (`-1` for a random seed).
+/// - `mode`: How the generated terrain was laid out.
+/// - `noise_type`: The noise used for terrain generation.
+/// - `noise_params`: The parameters of the noise.
+/// - `rng_seed`: The seed of the simulation's random stream.
+/// - `falling_sand`: Whether falling sand was enabled.
+/// - `collapse`: Whether the collapse of unsupported terrain was enabled.
+/// - `events`: The actions of the user, in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub terrain_width: usize,
+    pub terrain_height: usize,
+    pub cell_size: f32,
+    pub delta: f32,
+    pub seed: i64,
+    pub mode: GenerationMode,
+    pub noise_type: NoiseType,
+    pub noise_params: NoiseParams,
+    pub rng_seed: u64,
+    pub falling_sand: bool,
+    pub collapse: bool,
+    pub events: Vec<ReplayEvent>,
+}
+
+/// # Methods
+///
+/// - `save(path: &str) -> Result<(), String>`:
+///   Writes the replay to a RON file.
+/// - `load(path: &str) -> Result<Self, String>`:
+///   Reads a replay from a RON file.
+/// - `record(tick: u64, action: ReplayAction)`:
+///   Appends an action.
+/// - `actions_at(tick: u64) -> impl Iterator<Item = &ReplayAction>`:
+///   Returns the actions that happened at a tick, in order.
+/// - `last_tick() -> u64`:
+///   Returns the tick of the last action.
+impl Replay {
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|e| e.to_string())?;
+        fs::write(path, source).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        let replay: Self = ron::from_str(&source).map_err(|e| format!("{}: {}", path, e))?;
+        replay.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(replay)
+    }
+
+    pub fn record(&mut self, tick: u64, action: ReplayAction) {
+        self.events.push(ReplayEvent { tick, action });
+    }
+
+    pub fn actions_at(&self, tick: u64) -> impl Iterator<Item = &ReplayAction> {
+        // Events are recorded in tick order
+        let start = self.events.partition_point(|event| event.tick < tick);
+        self.events[start..].iter().take_while(move |event| event.tick == tick).map(|event| &event.action)
+    }
+
+    pub fn last_tick(&self) -> u64 {
+        self.events.last().map_or(0, |event| event.tick)
+    }
+
+    // Check that the replay can be played back
+    fn validate(&self) -> Result<(), String> {
+        if self.version != REPLAY_VERSION {
+            return Err(format!("unsupported replay version {} (expected {})", self.version, REPLAY_VERSION));
+        }
+        if self.terrain_width == 0 || self.terrain_height == 0 {
+            return Err(format!("the terrain must not be empty ({}x{})", self.terrain_width, self.terrain_height));
+        }
+        if !(self.delta.is_finite() && self.delta > 0.0) {
+            return Err(format!("the time step must be a finite, positive number (found {})", self.delta));
+        }
+        if !(self.cell_size.is_finite() && self.cell_size > 0.0) {
+            return Err(format!("the cell size must be a finite, positive number (found {})", self.cell_size));
+        }
+
+        // Actions are looked up by tick, in order
+        if let Some(pair) = self.events.windows(2).find(|pair| pair[1].tick < pair[0].tick) {
+            return Err(format!("the action at tick {} is recorded after the one at tick {}", pair[1].tick, pair[0].tick));
+        }
+        for event in &self.events {
+            match event.action {
+                ReplayAction::SetCellSize { cell_size } if !(cell_size.is_finite() && cell_size > 0.0) => {
+                    return Err(format!("tick {}: the cell size must be a finite, positive number (found {})", event.tick, cell_size));
+                }
+                ReplayAction::Resize { width, height } if width == 0 || height == 0 => {
+                    return Err(format!("tick {}: the terrain must not be empty ({}x{})", event.tick, width, height));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Overrides};
+    use crate::effect::EffectRegistry;
+    use crate::layers::TerrainLayers;
+    use crate::materials::MaterialRegistry;
+    use crate::simulation::Simulation;
+
+    // An empty replay of a small world
+    fn replay() -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            terrain_width: 120,
+            terrain_height: 80,
+            cell_size: 5.0,
+            delta: 1.0 / 15.0,
+            seed: 9,
+            mode: GenerationMode::Field,
+            noise_type: NoiseType::Perlin,
+            noise_params: NoiseParams::default(),
+            rng_seed: 99,
+            falling_sand: true,
+            collapse: true,
+            events: Vec::new(),
+        }
+    }
+
+    // Play a replay back on a simulation started from its settings, returning the cells of the world
+    fn play(replay: &Replay) -> Vec<(u8, f32)> {
+        let config = Config {
+            terrain_width: replay.terrain_width,
+            terrain_height: replay.terrain_height,
+            cell_size: replay.cell_size,
+            seed: replay.seed,
+            falling_sand: replay.falling_sand,
+            collapse: replay.collapse,
+            ..Config::default()
+        };
+        let materials = MaterialRegistry::default();
+        let layers = TerrainLayers::builtin(&materials).unwrap();
+        let mut simulation = Simulation::new(&config, None, layers, materials, EffectRegistry::builtin(&Overrides::new()).unwrap());
+        simulation.reseed(replay.rng_seed);
+
+        while simulation.tick() <= replay.last_tick() + 30 {
+            for action in replay.actions_at(simulation.tick()) {
+                match action {
+                    ReplayAction::Spawn { x, y, effect } => simulation.spawn_effect(*x, *y, simulation.behaviors().id(effect).unwrap()),
+                    ReplayAction::Pour { x, y, material, radius } => {
+                        let material = simulation.materials().id(material).unwrap();
+                        simulation.pour(*x, *y, material, *radius);
+                    }
+                    _ => unreachable!("only effects and pours are recorded"),
+                }
+            }
+            simulation.step(replay.delta);
+        }
+        simulation.to_grid().iter().flatten().map(|cell| (cell.material.0, cell.durability)).collect()
+    }
+
+    #[test]
+    fn actions_are_looked_up_by_tick() {
+        let mut replay = replay();
+        replay.record(2, ReplayAction::Reset);
+        replay.record(5, ReplayAction::Select { effect: "Lightning".to_string() });
+        replay.record(5, ReplayAction::SetCellSize { cell_size: 4.0 });
+
+        assert_eq!(replay.actions_at(2).collect::<Vec<_>>(), [&ReplayAction::Reset]);
+        assert_eq!(replay.actions_at(3).count(), 0);
+        assert_eq!(replay.actions_at(5).count(), 2);
+        assert_eq!(replay.last_tick(), 5);
+    }
+
+    #[test]
+    fn saved_replay_plays_back_the_same() {
+        let mut recorded = replay();
+        recorded.record(0, ReplayAction::Spawn { x: 200.0, y: 150.0, effect: "Explosion".to_string() });
+        recorded.record(10, ReplayAction::Pour { x: 300.0, y: 50.0, material: "water".to_string(), radius: 4 });
+        recorded.record(12, ReplayAction::Spawn { x: 400.0, y: 200.0, effect: "Bubbles".to_string() });
+
+        let path = std::env::temp_dir().join(format!("{}-session.ron", std::process::id()));
+        let path = path.to_str().unwrap();
+        recorded.save(path).unwrap();
+        let loaded = Replay::load(path);
+        let _ = fs::remove_file(path);
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.events, recorded.events);
+        assert_eq!(play(&loaded), play(&recorded));
+        assert_ne!(play(&loaded), play(&replay()));
+    }
+
+    #[test]
+    fn rejects_invalid_replays() {
+        assert!(replay().validate().is_ok());
+
+        let error = |replay: Replay| replay.validate().unwrap_err();
+        assert!(error(Replay { version: 0, ..replay() }).contains("unsupported replay version"));
+        assert!(error(Replay { terrain_height: 0, ..replay() }).contains("must not be empty"));
+        assert!(error(Replay { delta: 0.0, ..replay() }).contains("time step"));
+        assert!(error(Replay { cell_size: f32::NAN, ..replay() }).contains("cell size"));
+
+        let mut unordered = replay();
+        unordered.record(4, ReplayAction::Reset);
+        unordered.record(3, ReplayAction::Reset);
+        assert_eq!(error(unordered), "the action at tick 3 is recorded after the one at tick 4");
+
+        let mut cell_size = replay();
+        cell_size.record(1, ReplayAction::SetCellSize { cell_size: -2.0 });
+        assert!(error(cell_size).starts_with("tick 1: the cell size"));
+
+        let mut resize = replay();
+        resize.record(1, ReplayAction::Resize { width: 0, height: 10 });
+        assert!(error(resize).starts_with("tick 1: the terrain must not be empty"));
+    }
+}
@@ -0,0 +1,323 @@
+// -----------------------------------------------------------------------------
+// File: save.rs
+// Description: Snapshots of the terrain, saved to and loaded from disk in a
+//              versioned binary format or in RON.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::generationmodes::GenerationMode;
+use crate::noisegenerator::NoiseParams;
+use crate::noisetypes::NoiseType;
+
+/// The version of the save format written by this build.
+pub const SAVE_VERSION: u32 = 1;
+
+// Magic bytes at the start of a binary save
+const MAGIC: &[u8; 4] = b"TDSV";
+
+/// A snapshot of the terrain, enough to restore a destroyed landscape.
+///
+/// Materials are saved by name, so that a save stays valid when materials are added
+/// to the material file or reordered.
+///
+/// # Fields
+/// - `version`: The version of the save format.
+/// - `terrain_width`: The width of the terrain, in cells.
+/// - `terrain_height`: The height of the terrain, in cells.
+/// - `cell_size`: The size of a cell, in world units (pixels).
+/// - `seed`: The seed the terrain was generated from.
+/// - `mode`: How the terrain was laid out.
+/// - `noise_type`: The noise the terrain was generated with.
+/// - `noise_params`: The parameters of the noise.
+/// - `materials`: The names of the materials used by the cells.
+/// - `cells`: Every cell as `(material, durability)`, the material indexing into `materials`,
+///   in column-major order (`x * terrain_height + y`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainSnapshot {
+    pub version: u32,
+    pub terrain_width: usize,
+    pub terrain_height: usize,
+    pub cell_size: f32,
+    pub seed: i64,
+    pub mode: GenerationMode,
+    pub noise_type: NoiseType,
+    pub noise_params: NoiseParams,
+    pub materials: Vec<String>,
+    pub cells: Vec<(u8, f32)>,
+}
+
+/// # Methods
+///
+/// - `save(path: &str) -> Result<(), String>`:
+///   Writes the snapshot to a file, in RON if the file name ends with `.ron`, in binary otherwise.
+/// - `load(path: &str) -> Result<Self, String>`:
+///   Reads a snapshot from a file, in RON if the file name ends with `.ron`, in binary otherwise.
+/// - `to_bytes() -> Vec<u8>`:
+///   Encodes the snapshot in the binary format.
+/// - `from_bytes(bytes: &[u8]) -> Result<Self, String>`:
+///   Decodes a snapshot from the binary format.
+impl TerrainSnapshot {
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let bytes = if is_ron(path) {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|e| e.to_string())?
+                .into_bytes()
+        } else {
+            self.to_bytes()
+        };
+        fs::write(path, bytes).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let snapshot = if is_ron(path) {
+            let source = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            ron::from_str::<Self>(&source).map_err(|e| format!("{}: {}", path, e))?
+        } else {
+            let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))?
+        };
+        snapshot.validate().map_err(|e| format!("{}: {}", path, e))?;
+        Ok(snapshot)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(64 + self.cells.len() * 5);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&(self.terrain_width as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.terrain_height as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.cell_size.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        write_str(&mut bytes, &self.mode.to_string());
+        write_str(&mut bytes, &noise_name(self.noise_type));
+
+        // Noise parameters, each optional one prefixed with whether it is set
+        bytes.extend_from_slice(&self.noise_params.frequency.to_le_bytes());
+        write_option(&mut bytes, self.noise_params.octaves.map(|octaves| (octaves as u32).to_le_bytes()));
+        write_option(&mut bytes, self.noise_params.lacunarity.map(f64::to_le_bytes));
+        write_option(&mut bytes, self.noise_params.persistence.map(f64::to_le_bytes));
+
+        // Material palette
+        bytes.extend_from_slice(&(self.materials.len() as u16).to_le_bytes());
+        for name in &self.materials {
+            write_str(&mut bytes, name);
+        }
+
+        // Cells
+        for &(material, durability) in &self.cells {
+            bytes.push(material);
+            bytes.extend_from_slice(&durability.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a terrain save".to_string());
+        }
+
+        let version = u32::from_le_bytes(reader.array()?);
+        if version != SAVE_VERSION {
+            return Err(format!("unsupported save version {} (expected {})", version, SAVE_VERSION));
+        }
+
+        let terrain_width = u32::from_le_bytes(reader.array()?) as usize;
+        let terrain_height = u32::from_le_bytes(reader.array()?) as usize;
+        let cell_size = f32::from_le_bytes(reader.array()?);
+        let seed = i64::from_le_bytes(reader.array()?);
+        let mode_name = reader.string()?;
+        let mode = GenerationMode::from_str(&mode_name, true).map_err(|_| format!("unknown generation mode \"{}\"", mode_name))?;
+        let noise = reader.string()?;
+        let noise_type = NoiseType::from_str(&noise, true).map_err(|_| format!("unknown noise type \"{}\"", noise))?;
+        let noise_params = NoiseParams {
+            frequency: f64::from_le_bytes(reader.array()?),
+            octaves: reader.option()?.map(|bytes| u32::from_le_bytes(bytes) as usize),
+            lacunarity: reader.option()?.map(f64::from_le_bytes),
+            persistence: reader.option()?.map(f64::from_le_bytes),
+        };
+
+        // Material palette
+        let count = u16::from_le_bytes(reader.array()?) as usize;
+        let materials = (0..count).map(|_| reader.string()).collect::<Result<Vec<_>, _>>()?;
+
+        // Cells
+        let mut cells = Vec::with_capacity((terrain_width * terrain_height).min(bytes.len() / 5));
+        for _ in 0..terrain_width * terrain_height {
+            let [material] = reader.array()?;
+            cells.push((material, f32::from_le_bytes(reader.array()?)));
+        }
+
+        Ok(Self { version, terrain_width, terrain_height, cell_size, seed, mode, noise_type, noise_params, materials, cells })
+    }
+
+    // Check that the snapshot is consistent
+    fn validate(&self) -> Result<(), String> {
+        if self.version != SAVE_VERSION {
+            return Err(format!("unsupported save version {} (expected {})", self.version, SAVE_VERSION));
+        }
+        if self.terrain_width == 0 || self.terrain_height == 0 {
+            return Err(format!("the terrain must not be empty ({}x{})", self.terrain_width, self.terrain_height));
+        }
+        if !(self.cell_size.is_finite() && self.cell_size > 0.0) {
+            return Err(format!("the cell size must be a finite, positive number (found {})", self.cell_size));
+        }
+        if self.cells.len() != self.terrain_width * self.terrain_height {
+            return Err(format!(
+                "expected {} cells for a {}x{} terrain, found {}",
+                self.terrain_width * self.terrain_height, self.terrain_width, self.terrain_height, self.cells.len()
+            ));
+        }
+        if let Some(&(material, _)) = self.cells.iter().find(|(m, _)| *m as usize >= self.materials.len()) {
+            return Err(format!("unknown material index {}", material));
+        }
+        Ok(())
+    }
+}
+
+// Whether a save file uses the RON format
+fn is_ron(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ron"))
+}
+
+// The command-line name of a noise type, stable across versions
+fn noise_name(noise_type: NoiseType) -> String {
+    noise_type.to_possible_value().map_or_else(|| "perlin".to_string(), |value| value.get_name().to_string())
+}
+
+// Write a length-prefixed UTF-8 string
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    bytes.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+// Write an optional value, prefixed with whether it is set
+fn write_option<const N: usize>(bytes: &mut Vec<u8>, value: Option<[u8; N]>) {
+    match value {
+        Some(value) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&value);
+        }
+        None => bytes.push(0),
+    }
+}
+
+// A cursor over the bytes of a binary save
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    // Take the next `n` bytes
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.offset + n;
+        let slice = self.bytes.get(self.offset..end).ok_or("unexpected end of file")?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    // Take the next `N` bytes as an array
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    // Take an optional value of `N` bytes, prefixed with whether it is set
+    fn option<const N: usize>(&mut self) -> Result<Option<[u8; N]>, String> {
+        let [set] = self.array()?;
+        if set == 0 { Ok(None) } else { self.array().map(Some) }
+    }
+
+    // Take a length-prefixed UTF-8 string
+    fn string(&mut self) -> Result<String, String> {
+        let len = u16::from_le_bytes(self.array()?) as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Overrides};
+    use crate::effect::EffectRegistry;
+    use crate::layers::TerrainLayers;
+    use crate::materials::MaterialRegistry;
+    use crate::simulation::Simulation;
+
+    // A small world generated from a seed, with the built-in definitions
+    fn simulation(seed: i64) -> Simulation {
+        let config = Config { terrain_width: 100, terrain_height: 70, seed, ..Config::default() };
+        let materials = MaterialRegistry::default();
+        let layers = TerrainLayers::builtin(&materials).unwrap();
+        Simulation::new(&config, None, layers, materials, EffectRegistry::builtin(&Overrides::new()).unwrap())
+    }
+
+    // Every cell of the world, as `(material, durability)`
+    fn cells(simulation: &Simulation) -> Vec<(u8, f32)> {
+        simulation.to_grid().iter().flatten().map(|cell| (cell.material.0, cell.durability)).collect()
+    }
+
+    // Save a damaged terrain to a file, load it back into another world and compare
+    fn round_trip(file_name: &str) {
+        let mut saved = simulation(11);
+        for x in 20..60 {
+            saved.damage_terrain_at(x, 40, 1000.0, true);
+        }
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), file_name));
+        let path = path.to_str().unwrap();
+        saved.snapshot().save(path).unwrap();
+        let snapshot = TerrainSnapshot::load(path);
+        let _ = fs::remove_file(path);
+
+        let mut restored = simulation(12);
+        assert_ne!(cells(&restored), cells(&saved));
+        restored.restore(&snapshot.unwrap()).unwrap();
+        assert_eq!(cells(&restored), cells(&saved));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        round_trip("terrain.sav");
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        round_trip("terrain.ron");
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(TerrainSnapshot::from_bytes(b"PNG?").unwrap_err(), "not a terrain save");
+
+        let bytes = simulation(1).snapshot().to_bytes();
+        assert!(TerrainSnapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        let snapshot = simulation(1).snapshot();
+        assert!(snapshot.validate().is_ok());
+
+        let empty = TerrainSnapshot { terrain_width: 0, cells: Vec::new(), ..snapshot.clone() };
+        assert!(empty.validate().unwrap_err().contains("must not be empty"));
+        for cell_size in [0.0, -5.0, f32::NAN] {
+            let invalid = TerrainSnapshot { cell_size, ..snapshot.clone() };
+            assert!(invalid.validate().unwrap_err().contains("cell size"));
+        }
+        let truncated = TerrainSnapshot { cells: snapshot.cells[1..].to_vec(), ..snapshot.clone() };
+        assert!(truncated.validate().is_err());
+        let unknown = TerrainSnapshot { materials: Vec::new(), ..snapshot };
+        assert!(unknown.validate().unwrap_err().contains("unknown material index"));
+    }
+}
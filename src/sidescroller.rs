@@ -0,0 +1,81 @@
+// -----------------------------------------------------------------------------
+// File: sidescroller.rs
+// Description: Generation of a side-view landscape: a surface line, ground
+//              layers below it and caves.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use rayon::prelude::*;
+
+use crate::cell::Cell;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::noisegenerator::NoiseGenerator;
+
+// Average height of the surface, as a fraction of the terrain height from the top
+const SURFACE_LEVEL: f64 = 0.4;
+
+// Largest move of the surface away from its average height, as a fraction of the terrain height
+const SURFACE_AMPLITUDE: f64 = 0.3;
+
+// Horizontal stretch of the surface noise, so that hills are wider than caves
+const SURFACE_STRETCH: f64 = 0.3;
+
+// Thickness of the grass on top of the ground, in cells
+const GRASS_DEPTH: usize = 3;
+
+// Cave noise value above which the ground is carved
+const CAVE_THRESHOLD: f64 = 0.3;
+
+/// Looks up the grass and rock materials the side-view landscape is made of.
+///
+/// # Parameters
+/// - `materials`: The material registry.
+///
+/// # Returns
+/// The grass and rock materials, or an error naming the first one missing from the registry.
+pub fn ground_materials(materials: &MaterialRegistry) -> Result<(MaterialId, MaterialId), String> {
+    let material = |name: &str| materials.id(name).ok_or(format!("unknown material \"{}\"", name));
+    Ok((material("grass")?, material("rock")?))
+}
+
+/// Fills the terrain with a side-view landscape.
+///
+/// The surface height of each column is read from the surface noise along a single row, stretched
+/// horizontally, so that the surface forms a continuous line of hills. Everything above it is air; below it, the ground is made of grass
+/// on the top few cells, then rock. Caves are carved where the cave noise is above a threshold.
+///
+/// # Parameters
+/// - `terrain`: The columns to fill, indexed as `terrain[x][y]` with `y` growing downwards, each spanning the whole height.
+/// - `origin`: The index of the first column in the world.
+/// - `surface`: The noise giving the height of the surface.
+/// - `caves`: The noise giving the caves, typically the same noise with another seed.
+/// - `materials`: The material registry.
+/// - `ground`: The grass and rock materials, as given by `ground_materials`.
+pub fn generate_sidescroller(terrain: &mut [Vec<Cell>], origin: usize, surface: &NoiseGenerator, caves: &NoiseGenerator, materials: &MaterialRegistry, ground: (MaterialId, MaterialId)) {
+    let height = terrain.first().map_or(0, |column| column.len());
+    let (grass, rock) = ground;
+    let cell = |material: MaterialId| Cell { material, durability: materials.get(material).durability };
+
+    // Columns are independent, so they are filled in parallel
+    terrain.par_iter_mut().enumerate().for_each(|(i, column)| {
+        let x = origin + i;
+
+        // Higher noise values raise the surface
+        let level = SURFACE_LEVEL - SURFACE_AMPLITUDE * surface.get(x as f64 * SURFACE_STRETCH, 0.0);
+        let top = (level.clamp(0.0, 1.0) * height as f64) as usize;
+
+        for (y, target) in column.iter_mut().enumerate() {
+            *target = if y < top || caves.get(x as f64, y as f64) > CAVE_THRESHOLD {
+                cell(MaterialId::AIR)
+            } else if y < top + GRASS_DEPTH {
+                cell(grass)
+            } else {
+                cell(rock)
+            };
+        }
+    });
+}
@@ -0,0 +1,1188 @@
+// -----------------------------------------------------------------------------
+// File: simulation.rs
+// Description: Headless simulation core owning the terrain, effects and quadtree.
+//              Can be stepped forward without a window or an audio device.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use ggez::graphics::Rect;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::f32::consts::TAU;
+use std::ops::Range;
+
+use crate::cell::Cell;
+use crate::chunks::{CHUNK_SIZE, ChunkedTerrain};
+use crate::collapse::{Debris, find_unsupported};
+use crate::config::Config;
+use crate::effect::{Blast, Contact, Effect, EffectId, EffectRegistry};
+use crate::fallingsand::update_falling_sand;
+use crate::liquids::update_liquids;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::generationmodes::GenerationMode;
+use crate::layers::TerrainLayers;
+use crate::sidescroller::{generate_sidescroller, ground_materials};
+use crate::noisegenerator::{NoiseGenerator, NoiseParams};
+use crate::noisegraph::NoiseGraph;
+use crate::noisetypes::NoiseType;
+use crate::save::{SAVE_VERSION, TerrainSnapshot};
+
+// Margin around the view within which chunks are loaded, in chunks
+const LOAD_MARGIN: usize = 1;
+
+// Margin around the view beyond which unmodified chunks are unloaded, in chunks
+const UNLOAD_MARGIN: usize = 3;
+
+/// An event produced by the simulation that a front-end (renderer, audio) may react to.
+///
+/// # Variants
+///
+/// - `CellChanged`: The cell at `(x, y)` changed and should be redrawn.
+/// - `TerrainGenerated`: The whole terrain was regenerated, replaced or rescaled, and every chunk should be drawn again.
+/// - `ChunkLoaded`: The chunk at `(cx, cy)`, in chunks, was loaded and should be drawn.
+/// - `ChunkUnloaded`: The chunk at `(cx, cy)`, in chunks, was unloaded and should no longer be drawn.
+/// - `Sound`: A sound should be played from `path` at the given `volume`.
+/// - `Explosion`: An explosion of the given `radius` (in world units) detonated at `(x, y)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationEvent {
+    CellChanged { x: usize, y: usize },
+    TerrainGenerated,
+    ChunkLoaded { cx: usize, cy: usize },
+    ChunkUnloaded { cx: usize, cy: usize },
+    Sound { path: String, volume: f32 },
+    Explosion { x: f32, y: f32, radius: f32 },
+}
+
+/// The outcome of updating a single effect, gathered during the parallel pass
+/// and applied sequentially, in effect order, afterwards.
+///
+/// # Fields
+/// - `damage`: Damage requests as `(tx, ty, amount, ignore_durability)`.
+/// - `children`: Sub-effects the effect wants to spawn.
+/// - `sound`: A sound to play, if the effect bounced off an edge.
+/// - `detonation`: The position where the effect detonated and its blast, if it exploded.
+#[derive(Default)]
+struct EffectUpdate {
+    damage: Vec<(usize, usize, f32, bool)>,
+    children: Vec<Effect>,
+    sound: Option<&'static str>,
+    detonation: Option<(f32, f32, Blast)>,
+}
+
+/// The `Simulation` struct holds the whole destruction logic of the game,
+/// independently of any rendering or audio back-end.
+///
+/// # Fields
+/// - `terrain`: The terrain, stored in chunks generated on demand around the view, the effects and the debris.
+/// - `effects`: A vector of active effects in the simulation.
+/// - `behaviors`: The registry of the effect types, giving the behaviour of each effect.
+/// - `materials`: The registry of the materials the terrain is made of.
+/// - `seed`: The seed used for terrain generation (`-1` for a random seed).
+/// - `actual_seed`: The seed the current terrain was generated from, drawn at random if `seed` is `-1`.
+/// - `rng_seed`: The seed of the simulation's random stream.
+/// - `rng`: The simulation's random stream, from which every random decision is drawn.
+/// - `mode`: How the generated terrain is laid out.
+/// - `ground`: The grass and rock materials of the sidescroller mode, or why the materials lack them.
+/// - `noise_generator`: An instance of `NoiseGenerator` for generating terrain noise.
+/// - `cave_generator`: The noise carving the caves in the sidescroller mode.
+/// - `noise_graph`: A graph of noise modules used instead of the noise generator, if any.
+/// - `layers`: The bands and depth rules turning the noise into materials.
+/// - `terrain_width`: The width of the terrain, in cells.
+/// - `terrain_height`: The height of the terrain, in cells.
+/// - `cell_size`: The size of a cell, in world units (pixels).
+/// - `view`: The part of the world being looked at, in world units, around which chunks are kept loaded.
+/// - `tick`: The number of steps simulated so far.
+/// - `falling_sand`: Whether granular materials fall and slide at each step.
+/// - `collapse`: Whether unsupported chunks of terrain collapse into falling debris.
+/// - `debris`: The chunks of terrain currently falling.
+/// - `events`: Events produced since the last call to `drain_events`.
+pub struct Simulation {
+    // Terrain
+    terrain: ChunkedTerrain,
+
+    // Effects
+    effects: Vec<Effect>,
+    behaviors: EffectRegistry,
+
+    // Materials
+    materials: MaterialRegistry,
+
+    // Terrain generation
+    seed: i64,
+    actual_seed: u32,
+    mode: GenerationMode,
+    ground: Result<(MaterialId, MaterialId), String>,
+    noise_generator: NoiseGenerator,
+    cave_generator: NoiseGenerator,
+    noise_graph: Option<NoiseGraph>,
+    layers: TerrainLayers,
+
+    // Random stream
+    rng_seed: u64,
+    rng: StdRng,
+
+    // Dimensions
+    terrain_width: usize,
+    terrain_height: usize,
+    cell_size: f32,
+
+    // Area kept loaded
+    view: Rect,
+
+    // Simulation time
+    tick: u64,
+
+    // Falling sand pass
+    falling_sand: bool,
+
+    // Structural integrity
+    collapse: bool,
+    debris: Vec<Debris>,
+
+    // Events waiting to be consumed by the front-end
+    events: Vec<SimulationEvent>,
+}
+
+/// # Methods
+///
+/// ## `new`
+/// Creates a new simulation with the terrain dimensions, cell size, seed, generation mode, noise type and parameters, and passes
+/// of a configuration, an optional noise graph, terrain layers, materials and effect types, and generates the initial terrain. A non-negative seed also seeds the random stream,
+/// otherwise the random stream is seeded from entropy. Panics in the sidescroller mode if the materials lack grass or rock,
+/// which `ground_materials` tells beforehand. The ground materials are looked up once, so that generating the terrain never fails.
+///
+/// ## `generate_terrain`
+/// Reseeds the noise generators and the noise graph, unloads every chunk, and loads the chunks around the view again.
+///
+/// ## `seed_generators`
+/// Reseeds the noise generator, the cave generator and the noise graph with the seed the terrain is generated from.
+///
+/// ## `generate_strip`
+/// Generates a column of chunks using the noise graph if any, or the noise generator, maps the noise to materials with the terrain layers
+/// (or lays out a side view in the sidescroller mode), and applies the depth rules, all in parallel over the columns.
+/// Chunks are generated a whole column at a time since the depth rules layer whole columns.
+///
+/// ## `set_view`
+/// Moves the view, loading the chunks around it and unloading the unmodified chunks far from it.
+///
+/// ## `stream_chunks`
+/// Loads the chunks around the view, the effects and the debris, and unloads the unmodified chunks far from all of them.
+/// Modified chunks stay loaded, since they could not be generated again.
+///
+/// ## `to_grid`
+/// Returns the whole terrain as a grid, generating the unloaded chunks without loading them.
+///
+/// ## `grid`
+/// Returns the cells within the given columns and rows as a grid, generating the unloaded chunks among them without loading them.
+///
+/// ## `cells_in`
+/// Returns the columns and rows of the cells covered by a rectangle in world units, clamped to the world.
+///
+/// ## `damage_terrain_at`
+/// Damages the terrain at a specific cell, optionally ignoring durability.
+/// Returns the material of the cell after damage is applied.
+///
+/// ## `reseed`
+/// Reseeds the random stream, so that the following random decisions are reproduced.
+///
+/// ## `spawn_effect`
+/// Spawns a new effect of the given type at a specified position.
+///
+/// ## `set_noise_type`
+/// Switches the noise used for terrain generation and regenerates the terrain.
+///
+/// ## `reconfigure`
+/// Applies another configuration: the passes are switched, effects and the view are scaled to a new cell size,
+/// and the terrain is generated again if a generation setting changed, or resized otherwise.
+/// Fails, leaving the simulation unchanged, if the configuration asks for the sidescroller mode and the materials lack grass or rock.
+///
+/// ## `step`
+/// Advances the simulation by `dt` simulated seconds: streams the chunks, moves and ages effects, handles collisions,
+/// spawns sub-effects, applies damage and removes effects older than their lifetime.
+/// Chunks of terrain cut loose by the damage then turn into debris that falls and settles back
+/// into the grid (when enabled), liquids flow, and granular materials fall (when enabled), within the modified chunks
+/// and the chunks just generated with liquid or granular cells, which are then at rest unless their cells moved.
+/// The chunks these reach are generated as needed, so that the outcome does not depend on the view.
+///
+/// ## `pour`
+/// Fills the air cells within a radius around a position with a material, typically a liquid.
+///
+/// ## `snapshot`
+/// Captures the terrain, its dimensions, seed, generation mode, noise type and noise parameters, to be saved to disk.
+///
+/// ## `restore`
+/// Replaces the terrain with a snapshot of the same dimensions, clearing effects and debris,
+/// and reseeds the noise generators with the saved seed.
+/// Fails if the dimensions differ, if a material of the snapshot is unknown, or if the snapshot was generated
+/// in the sidescroller mode and the materials lack grass or rock.
+///
+/// ## `set_terrain`
+/// Replaces the terrain with a grid of the same dimensions (e.g. imported from an image),
+/// clearing effects and debris. Its chunks are all loaded and never unloaded.
+///
+/// ## `resize`
+/// Changes the dimensions of the world, e.g. to follow the window. The modified chunks still in the world are kept,
+/// completed from the noise where they grew, and the rest of the world is generated again from the same seed.
+///
+/// ## `drain_events`
+/// Returns and clears the events produced since the last call.
+impl Simulation {
+    pub fn new(config: &Config, noise_graph: Option<NoiseGraph>, layers: TerrainLayers, materials: MaterialRegistry, behaviors: EffectRegistry) -> Self {
+        let (terrain_width, terrain_height, cell_size, seed) = (config.terrain_width, config.terrain_height, config.cell_size, config.seed);
+
+        // Seed the random stream from the seed if provided
+        let rng_seed = if seed == -1 {
+            rand::rng().random()
+        } else {
+            seed as u64
+        };
+
+        let ground = ground_materials(&materials);
+        if config.mode == GenerationMode::Sidescroller
+            && let Err(e) = &ground
+        {
+            panic!("Cannot lay out the sidescroller terrain ({})", e);
+        }
+
+        let mut s = Simulation {
+            terrain: ChunkedTerrain::new(terrain_width, terrain_height, cell_size),
+            effects: vec![],
+            behaviors,
+            materials,
+            seed,
+            actual_seed: seed.max(0) as u32,
+            mode: config.mode,
+            ground,
+            // A random seed is drawn when generating the terrain
+            noise_generator: NoiseGenerator::new(config.noise_type, seed.max(0) as u32, config.noise_params),
+            cave_generator: NoiseGenerator::new(config.noise_type, seed.max(0) as u32, config.noise_params),
+            noise_graph,
+            layers,
+            rng_seed,
+            rng: StdRng::seed_from_u64(rng_seed),
+            terrain_width,
+            terrain_height,
+            cell_size,
+            // The whole world is in view until told otherwise
+            view: Rect::new(0.0, 0.0, terrain_width as f32 * cell_size, terrain_height as f32 * cell_size),
+            tick: 0,
+            falling_sand: config.falling_sand,
+            collapse: config.collapse,
+            debris: Vec::new(),
+            events: Vec::new(),
+        };
+        s.generate_terrain();
+        s
+    }
+
+    /// Returns the seed of the simulation's random stream.
+    pub fn rng_seed(&self) -> u64 {
+        self.rng_seed
+    }
+
+    /// Reseeds the simulation's random stream, e.g. to replay a recorded session.
+    pub fn reseed(&mut self, rng_seed: u64) {
+        self.rng_seed = rng_seed;
+        self.rng = StdRng::seed_from_u64(rng_seed);
+    }
+
+    /// Returns the number of steps simulated so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns the noise used for terrain generation.
+    pub fn noise_type(&self) -> NoiseType {
+        self.noise_generator.noise_type()
+    }
+
+    /// Returns the parameters shaping the noise used for terrain generation.
+    pub fn noise_params(&self) -> NoiseParams {
+        self.noise_generator.params()
+    }
+
+    /// Switches the noise used for terrain generation and regenerates the terrain.
+    pub fn set_noise_type(&mut self, noise_type: NoiseType) {
+        self.noise_generator.set_noise_type(noise_type);
+        self.generate_terrain();
+    }
+
+    /// Applies a new configuration to the running simulation.
+    pub fn reconfigure(&mut self, config: &Config) -> Result<(), String> {
+        self.check_mode(config.mode)?;
+
+        self.falling_sand = config.falling_sand;
+        self.collapse = config.collapse;
+
+        // Effects and the view stay over the same cells at another cell size
+        if config.cell_size != self.cell_size {
+            let ratio = config.cell_size / self.cell_size;
+            for eff in &mut self.effects {
+                eff.position = (eff.position.0 * ratio, eff.position.1 * ratio);
+            }
+            self.view = Rect::new(self.view.x * ratio, self.view.y * ratio, self.view.w * ratio, self.view.h * ratio);
+            self.cell_size = config.cell_size;
+            self.terrain.set_cell_size(config.cell_size);
+            self.events.push(SimulationEvent::TerrainGenerated);
+        }
+
+        // Other generation settings give another terrain, otherwise the modified chunks are kept
+        let generation = (self.seed, self.mode, self.noise_generator.noise_type(), self.noise_generator.params());
+        if generation != (config.seed, config.mode, config.noise_type, config.noise_params) {
+            self.seed = config.seed;
+            self.mode = config.mode;
+            self.noise_generator.set_noise_type(config.noise_type);
+            self.noise_generator.set_params(config.noise_params);
+            self.terrain_width = config.terrain_width;
+            self.terrain_height = config.terrain_height;
+            self.terrain = ChunkedTerrain::new(config.terrain_width, config.terrain_height, config.cell_size);
+            self.generate_terrain();
+        } else {
+            self.resize(config.terrain_width, config.terrain_height);
+        }
+        Ok(())
+    }
+
+    // Check that the terrain can be laid out in a mode
+    fn check_mode(&self, mode: GenerationMode) -> Result<(), String> {
+        match (mode, &self.ground) {
+            (GenerationMode::Sidescroller, Err(e)) => Err(format!("cannot lay out the sidescroller terrain ({})", e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the chunks of terrain currently falling.
+    pub fn debris(&self) -> &Vec<Debris> {
+        &self.debris
+    }
+
+    /// Returns the chunked terrain.
+    pub fn terrain(&self) -> &ChunkedTerrain {
+        &self.terrain
+    }
+
+    /// Returns the part of the world being looked at, in world units.
+    pub fn view(&self) -> Rect {
+        self.view
+    }
+
+    /// Moves the view, streaming the chunks around it.
+    pub fn set_view(&mut self, view: Rect) {
+        self.view = view;
+        self.stream_chunks();
+    }
+
+    /// Returns the material registry.
+    pub fn materials(&self) -> &MaterialRegistry {
+        &self.materials
+    }
+
+    /// Returns the registry of the effect types.
+    pub fn behaviors(&self) -> &EffectRegistry {
+        &self.behaviors
+    }
+
+    /// Returns the active effects.
+    pub fn effects(&self) -> &Vec<Effect> {
+        &self.effects
+    }
+
+    /// Returns the width of the terrain, in cells.
+    pub fn terrain_width(&self) -> usize {
+        self.terrain_width
+    }
+
+    /// Returns the height of the terrain, in cells.
+    pub fn terrain_height(&self) -> usize {
+        self.terrain_height
+    }
+
+    /// Returns the size of a cell, in world units.
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns the width of the simulated world, in world units.
+    pub fn width(&self) -> f32 {
+        self.terrain_width as f32 * self.cell_size
+    }
+
+    /// Returns the height of the simulated world, in world units.
+    pub fn height(&self) -> f32 {
+        self.terrain_height as f32 * self.cell_size
+    }
+
+    /// Returns and clears the events produced since the last call.
+    pub fn drain_events(&mut self) -> Vec<SimulationEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Removes every active effect.
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+    }
+
+    /// Captures the terrain into a snapshot, saving the seed the terrain was actually generated from.
+    pub fn snapshot(&self) -> TerrainSnapshot {
+        // Save only the materials in use, by name
+        let mut materials: Vec<MaterialId> = Vec::new();
+        let mut cells = Vec::with_capacity(self.terrain_width * self.terrain_height);
+        for column in self.to_grid() {
+            for cell in column {
+                let index = match materials.iter().position(|&m| m == cell.material) {
+                    Some(index) => index,
+                    None => {
+                        materials.push(cell.material);
+                        materials.len() - 1
+                    }
+                };
+                cells.push((index as u8, cell.durability));
+            }
+        }
+
+        TerrainSnapshot {
+            version: SAVE_VERSION,
+            terrain_width: self.terrain_width,
+            terrain_height: self.terrain_height,
+            cell_size: self.cell_size,
+            seed: self.actual_seed as i64,
+            mode: self.mode,
+            noise_type: self.noise_generator.noise_type(),
+            noise_params: self.noise_generator.params(),
+            materials: materials.iter().map(|&m| self.materials.get(m).name.clone()).collect(),
+            cells,
+        }
+    }
+
+    /// Replaces the terrain with a snapshot.
+    pub fn restore(&mut self, snapshot: &TerrainSnapshot) -> Result<(), String> {
+        if snapshot.terrain_width != self.terrain_width
+            || snapshot.terrain_height != self.terrain_height
+            || snapshot.cell_size != self.cell_size
+        {
+            return Err(format!(
+                "the save is a {}x{} terrain of {} pixel cells, but the current terrain is {}x{} of {} pixel cells",
+                snapshot.terrain_width, snapshot.terrain_height, snapshot.cell_size,
+                self.terrain_width, self.terrain_height, self.cell_size
+            ));
+        }
+
+        // Map the saved material names to the current registry
+        let materials = snapshot.materials.iter()
+            .map(|name| self.materials.id(name).ok_or(format!("unknown material \"{}\"", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A reset lays out the terrain in the saved mode
+        self.check_mode(snapshot.mode)?;
+
+        let terrain = snapshot.cells
+            .chunks(self.terrain_height)
+            .map(|column| column.iter().map(|&(material, durability)| Cell { material: materials[material as usize], durability }).collect())
+            .collect();
+
+        // Reset generation to the saved seed, mode, noise and parameters, so that a reset gives back the original terrain
+        self.seed = snapshot.seed;
+        self.mode = snapshot.mode;
+        self.noise_generator.set_noise_type(snapshot.noise_type);
+        self.noise_generator.set_params(snapshot.noise_params);
+        self.seed_generators(snapshot.seed.max(0) as u32);
+
+        self.set_terrain(terrain)
+    }
+
+    /// Replaces the terrain with a grid of the same dimensions.
+    pub fn set_terrain(&mut self, terrain: Vec<Vec<Cell>>) -> Result<(), String> {
+        if terrain.len() != self.terrain_width || terrain.iter().any(|column| column.len() != self.terrain_height) {
+            return Err(format!("the terrain must be {}x{} cells", self.terrain_width, self.terrain_height));
+        }
+
+        // Load every chunk, as modified since it does not come from the noise
+        self.terrain.clear();
+        self.events.push(SimulationEvent::TerrainGenerated);
+        for cx in 0..self.terrain.chunks_x() {
+            for cy in 0..self.terrain.chunks_y() {
+                let (columns, rows) = self.terrain.chunk_bounds(cx, cy);
+                let cells = terrain[columns].iter().flat_map(|column| column[rows.clone()].iter().cloned()).collect();
+                self.terrain.insert(cx, cy, cells, true);
+                self.events.push(SimulationEvent::ChunkLoaded { cx, cy });
+            }
+        }
+
+        // Effects and debris belonged to the previous terrain
+        self.effects.clear();
+        self.debris.clear();
+        Ok(())
+    }
+
+    /// Resizes the world, keeping the modified chunks.
+    pub fn resize(&mut self, terrain_width: usize, terrain_height: usize) {
+        if (terrain_width, terrain_height) == (self.terrain_width, self.terrain_height) {
+            return;
+        }
+
+        // Modified chunks could not be generated again, so their cells are carried over
+        let modified: Vec<(usize, usize)> = self.terrain.loaded().into_iter()
+            .filter(|&(cx, cy)| self.terrain.is_dirty(cx, cy) && cx * CHUNK_SIZE < terrain_width && cy * CHUNK_SIZE < terrain_height)
+            .collect();
+        let mut kept = Vec::new();
+        for &(cx, cy) in &modified {
+            let (columns, rows) = self.terrain.chunk_bounds(cx, cy);
+            for x in columns.filter(|&x| x < terrain_width) {
+                for y in rows.clone().filter(|&y| y < terrain_height) {
+                    kept.extend(self.terrain.get(x, y).map(|cell| (x, y, cell.clone())));
+                }
+            }
+        }
+
+        self.terrain_width = terrain_width;
+        self.terrain_height = terrain_height;
+        self.terrain = ChunkedTerrain::new(terrain_width, terrain_height, self.cell_size);
+        self.events.push(SimulationEvent::TerrainGenerated);
+
+        // Load the modified chunks from the noise, then put their cells back
+        for (cx, cy) in modified {
+            self.load_chunks((cx..cx + 1, cy..cy + 1));
+        }
+        for (x, y, cell) in kept {
+            self.terrain.set(x, y, cell);
+        }
+        self.stream_chunks();
+    }
+
+    /// Returns the whole terrain as a grid indexed as `grid[x][y]`, generating the unloaded chunks without loading them.
+    pub fn to_grid(&self) -> Vec<Vec<Cell>> {
+        self.grid(0..self.terrain_width, 0..self.terrain_height)
+    }
+
+    /// Returns the cells within the given columns and rows as a grid indexed as `grid[x - columns.start][y - rows.start]`,
+    /// generating the unloaded chunks among them without loading them.
+    pub fn grid(&self, columns: Range<usize>, rows: Range<usize>) -> Vec<Vec<Cell>> {
+        let air = Cell { material: MaterialId::AIR, durability: 0.0 };
+        let (chunk_columns, chunk_rows) = self.chunks_of(columns.clone(), rows.clone(), 0);
+        let mut grid = Vec::with_capacity(columns.len());
+        for cx in chunk_columns {
+            // Unloaded chunks are generated again, as they were never modified
+            let strip = chunk_rows.clone()
+                .any(|cy| !self.terrain.is_loaded(cx, cy))
+                .then(|| self.generate_strip(cx));
+
+            let (chunk_cells, _) = self.terrain.chunk_bounds(cx, 0);
+            for (i, x) in chunk_cells.enumerate().filter(|(_, x)| columns.contains(x)) {
+                let column = rows.clone().map(|y| match self.terrain.get(x, y) {
+                    Some(loaded) => loaded.clone(),
+                    None => strip.as_ref().map_or_else(|| air.clone(), |strip| strip[i][y].clone()),
+                });
+                grid.push(column.collect());
+            }
+        }
+        grid
+    }
+
+    // Generate the terrain using the noise generator
+    pub fn generate_terrain(&mut self) {
+
+        // Generate the terrain using Perlin noise
+        let actual_seed = if self.seed == -1 {
+            self.rng.random_range(0..100_000)
+        } else {
+            self.seed as u32
+        };
+
+        // Update the seed
+        self.seed_generators(actual_seed);
+
+        // Chunks are generated again on demand
+        self.terrain.clear();
+
+        // Falling debris belonged to the previous terrain
+        self.debris.clear();
+
+        self.events.push(SimulationEvent::TerrainGenerated);
+        self.stream_chunks();
+    }
+
+    // Reseed the noise generators and the noise graph
+    fn seed_generators(&mut self, actual_seed: u32) {
+        self.actual_seed = actual_seed;
+        self.noise_generator.generate(actual_seed);
+        if let Some(graph) = &mut self.noise_graph {
+            graph.generate(actual_seed, self.noise_generator.noise_type(), self.noise_generator.params());
+        }
+
+        // Caves come from the same noise with another seed
+        self.cave_generator = NoiseGenerator::new(self.noise_generator.noise_type(), actual_seed.wrapping_add(1), self.noise_generator.params());
+    }
+
+    // Generate a column of chunks from the noise
+    fn generate_strip(&self, cx: usize) -> Vec<Vec<Cell>> {
+        let (columns, _) = self.terrain.chunk_bounds(cx, 0);
+        let mut strip = vec![vec![Cell { material: MaterialId::AIR, durability: 0.0 }; self.terrain_height]; columns.len()];
+
+        // The mode is only set to the sidescroller mode when its ground materials exist
+        match (self.mode, &self.ground) {
+            (GenerationMode::Sidescroller, Ok(ground)) => {
+                generate_sidescroller(&mut strip, columns.start, &self.noise_generator, &self.cave_generator, &self.materials, *ground);
+            }
+            _ => {
+                // Generate the terrain based on the noise, one column per task
+                let height = self.terrain_height as f64;
+                let (noise_generator, noise_graph, layers) = (&self.noise_generator, &self.noise_graph, &self.layers);
+                strip.par_iter_mut().enumerate().for_each(|(i, column)| {
+                    let x = columns.start + i;
+                    for (y, cell) in column.iter_mut().enumerate() {
+                        let val = match noise_graph {
+                            Some(graph) => graph.get(x as f64, y as f64, height),
+                            None => noise_generator.get(x as f64, y as f64),
+                        };
+
+                        // Assign the material of the band the noise value falls in
+                        *cell = layers.cell(val);
+                    }
+                });
+            }
+        }
+
+        // Layer the ground by depth
+        self.layers.apply_depth_rules(&mut strip, &self.materials);
+        strip
+    }
+
+    // Load the missing chunks within the given chunk ranges
+    fn load_chunks(&mut self, (chunk_columns, chunk_rows): (Range<usize>, Range<usize>)) {
+        // Generate the columns of chunks with a missing chunk in parallel
+        let missing: Vec<usize> = chunk_columns
+            .filter(|&cx| chunk_rows.clone().any(|cy| !self.terrain.is_loaded(cx, cy)))
+            .collect();
+        let strips: Vec<Vec<Vec<Cell>>> = missing.par_iter().map(|&cx| self.generate_strip(cx)).collect();
+
+        for (cx, strip) in missing.into_iter().zip(strips) {
+            for cy in chunk_rows.clone() {
+                if self.terrain.is_loaded(cx, cy) {
+                    continue;
+                }
+                let (_, rows) = self.terrain.chunk_bounds(cx, cy);
+                let cells: Vec<Cell> = strip.iter().flat_map(|column| column[rows.clone()].iter().cloned()).collect();
+
+                // Generated liquid and granular cells may not be at rest
+                let loose = cells.iter().any(|cell| {
+                    let material = self.materials.get(cell.material);
+                    material.liquid || material.granular
+                });
+                self.terrain.insert(cx, cy, cells, false);
+                if loose {
+                    self.terrain.mark_settling(cx, cy);
+                }
+                self.events.push(SimulationEvent::ChunkLoaded { cx, cy });
+            }
+        }
+    }
+
+    // The chunks covering the given cells, extended by a margin, in chunks
+    fn chunks_of(&self, columns: Range<usize>, rows: Range<usize>, margin: usize) -> (Range<usize>, Range<usize>) {
+        let chunks = |cells: Range<usize>, count: usize| {
+            if cells.is_empty() {
+                return 0..0;
+            }
+            (cells.start / CHUNK_SIZE).saturating_sub(margin)..(cells.end.div_ceil(CHUNK_SIZE) + margin).min(count)
+        };
+        (chunks(columns, self.terrain.chunks_x()), chunks(rows, self.terrain.chunks_y()))
+    }
+
+    /// Returns the cells covered by a rectangle in world units, clamped to the world.
+    pub fn cells_in(&self, rect: &Rect) -> (Range<usize>, Range<usize>) {
+        let cell = |value: f32, count: usize| ((value / self.cell_size).max(0.0) as usize).min(count);
+        (
+            cell(rect.x, self.terrain_width)..(cell(rect.right(), self.terrain_width) + 1).min(self.terrain_width),
+            cell(rect.y, self.terrain_height)..(cell(rect.bottom(), self.terrain_height) + 1).min(self.terrain_height),
+        )
+    }
+
+    // Load the chunks around the view, the effects, the debris and the modified chunks, and unload the unmodified chunks far from them
+    fn stream_chunks(&mut self) {
+        // Chunks needed by the effects, around their next moves, by the debris, down to where they may land,
+        // and by the passes, around the modified chunks
+        let mut active = Vec::new();
+        for eff in &self.effects {
+            let (columns, rows) = self.cells_in(&Rect::new(eff.position.0, eff.position.1, 0.0, 0.0));
+            active.push(self.chunks_of(columns, rows, 1));
+        }
+        for debris in &self.debris {
+            let (columns, rows) = debris.reach(0.0);
+            active.push(self.chunks_of(columns, rows, 1));
+        }
+        active.extend(self.terrain.modified_surroundings());
+
+        let (columns, rows) = self.cells_in(&self.view);
+        self.load_chunks(self.chunks_of(columns.clone(), rows.clone(), LOAD_MARGIN));
+        for chunks in active.clone() {
+            self.load_chunks(chunks);
+        }
+
+        // Unmodified chunks can be generated again when needed
+        let (kept_columns, kept_rows) = self.chunks_of(columns, rows, UNLOAD_MARGIN);
+        for (cx, cy) in self.terrain.loaded() {
+            let near = |(chunk_columns, chunk_rows): &(Range<usize>, Range<usize>)| chunk_columns.contains(&cx) && chunk_rows.contains(&cy);
+            if !self.terrain.is_dirty(cx, cy) && !near(&(kept_columns.clone(), kept_rows.clone())) && !active.iter().any(near) {
+                self.terrain.unload(cx, cy);
+                self.events.push(SimulationEvent::ChunkUnloaded { cx, cy });
+            }
+        }
+    }
+
+    // Damage the terrain at the specified position
+    pub fn damage_terrain_at(&mut self, x: usize, y: usize, amount: f32, ignore_durability: bool) -> MaterialId {
+        // Check if the position is within the terrain bounds
+        if x >= self.terrain_width || y >= self.terrain_height {
+            return MaterialId::AIR;
+        }
+
+        // Damage can reach chunks that are not loaded yet
+        self.load_chunks(self.chunks_of(x..x + 1, y..y + 1, 0));
+        let Some(cell) = self.terrain.get(x, y) else {
+            return MaterialId::AIR;
+        };
+
+        // Indestructible materials ignore all damage
+        let material = self.materials.get(cell.material);
+        if material.indestructible {
+            return cell.material;
+        }
+
+        let should_play_sound = cell.durability - amount <= 0.0;
+
+        // Play the material's sound if the cell is not air and the durability will be 0
+        if cell.material != MaterialId::AIR && (should_play_sound || ignore_durability) && self.rng.random_bool(0.10)
+            && let Some(sound) = &material.sound {
+            self.events.push(SimulationEvent::Sound { path: sound.clone(), volume: 0.2 });
+        }
+
+        // Apply damage if the cell is not air
+        if cell.material == MaterialId::AIR {
+            return MaterialId::AIR;
+        }
+        let Some(cell) = self.terrain.get_mut(x, y) else {
+            return MaterialId::AIR;
+        };
+        if !ignore_durability {
+            cell.durability -= amount;
+        }
+        if ignore_durability || cell.durability <= 0.0 {
+            cell.material = MaterialId::AIR;
+            cell.durability = 0.0;
+            self.events.push(SimulationEvent::CellChanged { x, y });
+        }
+        cell.material
+    }
+
+    // Spawn a new effect at the specified position
+    pub fn spawn_effect(&mut self, x: f32, y: f32, effect_type: EffectId) {
+
+        // Add a new effect to the list
+        self.effects.push(Effect {
+            effect_type,
+            position: (x, y),
+            direction: self.rng.random_range(0.0..TAU),
+            age: 0.0,
+            spawned: false,
+            rng: StdRng::seed_from_u64(self.rng.random()),
+        });
+    }
+
+    // Advance the simulation by `dt` simulated seconds
+    pub fn step(&mut self, dt: f32) {
+
+        // Advance the simulation clock
+        self.tick += 1;
+
+        // Load the chunks around the view, the effects and the debris
+        self.stream_chunks();
+
+        // Ensure the quadtrees of the modified chunks are up-to-date
+        self.terrain.update_quadtrees();
+
+        // Move the effects and let them damage the terrain
+        let destroyed = self.update_effects(dt);
+
+        // Detach the chunks no longer supported, and make the debris fall
+        if self.collapse {
+            self.detach_unsupported(&destroyed);
+            self.update_debris(dt);
+        }
+
+        // The passes reach the chunks around the chunks modified by this step
+        for chunks in self.terrain.modified_surroundings() {
+            self.load_chunks(chunks);
+        }
+
+        // Let liquids flow
+        let changed = update_liquids(&mut self.terrain, &self.materials, &mut self.rng);
+        self.record_changes(changed);
+
+        // Let granular materials fall into the air below them
+        if self.falling_sand {
+            let changed = update_falling_sand(&mut self.terrain, &self.materials, &mut self.rng);
+            self.record_changes(changed);
+        }
+
+        // The generated chunks swept are at rest, unless their cells moved
+        self.terrain.end_settling();
+    }
+
+    // Move the effects, apply their damage and remove the expired ones.
+    // Returns the cells destroyed during the update.
+    fn update_effects(&mut self, dt: f32) -> Vec<(usize, usize)> {
+
+        // Get the world dimensions
+        let width = self.width();
+        let height = self.height();
+        let radius = self.cell_size;
+
+        let terrain = &self.terrain;
+        let materials = &self.materials;
+        let behaviors = &self.behaviors;
+
+        // Update each effect in parallel, each one drawing from its own random stream.
+        // The results are collected in effect order so that applying them is reproducible.
+        let updates: Vec<EffectUpdate> = self.effects.par_iter_mut().map(|eff| {
+            let mut update = EffectUpdate::default();
+
+            // Age the effect
+            eff.age += dt;
+
+            let behavior = behaviors.get(eff.effect_type);
+
+            // Move the effect
+            behavior.advance(eff, dt);
+
+            // Check if the effect is outside the borders
+            let bounced = eff.position.0 <= 0.0
+                || eff.position.0 >= width
+                || eff.position.1 <= 0.0
+                || eff.position.1 >= height;
+
+            // Bounce the effect off the edges
+            eff.bounce(width, height);
+
+            if bounced {
+                let boing_sounds = ["resources/sounds/boing/boing.ogg", "resources/sounds/boing/boing_casseur.ogg"];
+                let index = eff.rng.random_range(0..boing_sounds.len());
+                update.sound = Some(boing_sounds[index]);
+            }
+
+            // Process cells near the effect
+            let query_rect = Rect::new(
+                eff.position.0 - radius,
+                eff.position.1 - radius,
+                radius * 2.0,
+                radius * 2.0,
+            );
+            let candidates = terrain.query(query_rect);
+            for candidate in candidates {
+                let cell_center_x = candidate.x;
+                let cell_center_y = candidate.y;
+                let distance = (eff.position.0 - cell_center_x).abs() + (eff.position.1 - cell_center_y).abs();
+                if distance <= radius {
+                    // Effects go through air and liquids
+                    let Some(cell) = terrain.get(candidate.tx, candidate.ty) else {
+                        continue;
+                    };
+                    let material = materials.get(cell.material);
+                    if cell.material == MaterialId::AIR || material.liquid {
+                        continue;
+                    }
+
+                    // Let the effect decide what it does to the cell
+                    let (dmg, ignore_durability) = match behavior.on_contact(eff, material) {
+                        Contact::Pass => continue,
+                        Contact::Damage { amount, ignore_durability } => (amount, ignore_durability),
+                        Contact::Detonate(blast) => {
+                            update.detonation = Some((eff.position.0, eff.position.1, blast));
+                            break;
+                        }
+                    };
+
+                    // Indestructible cells are never damaged and always block effects
+                    let remaining = if material.indestructible {
+                        f32::INFINITY
+                    } else {
+                        update.damage.push((candidate.tx, candidate.ty, dmg, ignore_durability));
+                        if ignore_durability { 0.0 } else { cell.durability - dmg }
+                    };
+                    if remaining > 0.0 {
+                        behavior.on_blocked(eff);
+                        break; // Bounce off the first intact cell
+                    }
+                }
+            }
+
+            // Some effects detonate on their own (e.g. when a fuse burns out)
+            if update.detonation.is_none()
+                && let Some(blast) = behavior.detonation(eff)
+            {
+                update.detonation = Some((eff.position.0, eff.position.1, blast));
+            }
+
+            // Propose sub-effects if not already spawned
+            if !eff.spawned {
+                update.children = behavior.spawn(eff);
+            }
+
+            update
+        }).collect();
+
+        // Apply the updates sequentially, in effect order
+        let mut new_effects = Vec::new();
+        let mut damage_requests = Vec::new();
+        let mut sounds_to_play = Vec::new();
+        let mut detonations = Vec::new();
+        let mut keep = Vec::with_capacity(self.effects.len());
+        let max_spawned = self.behaviors.max_spawned_per_step();
+        for (eff, update) in self.effects.iter_mut().zip(updates) {
+
+            // Detonated effects are removed
+            keep.push(update.detonation.is_none());
+            detonations.extend(update.detonation);
+
+            // Spawn sub-effects while the per-step cap is not reached
+            if !update.children.is_empty() && new_effects.len() < max_spawned {
+                let room = max_spawned - new_effects.len();
+                new_effects.extend(update.children.into_iter().take(room));
+                eff.spawned = true;
+            }
+            damage_requests.extend(update.damage);
+            sounds_to_play.extend(update.sound);
+        }
+
+        // Remove detonated effects and add new effects to the list
+        let mut keep = keep.into_iter();
+        self.effects.retain(|_| keep.next().unwrap_or(true));
+        self.effects.extend(new_effects);
+
+        // Process all collected damage requests after the loop
+        let mut destroyed = Vec::new();
+        for (tx, ty, dmg, ignore_durability) in damage_requests {
+            if self.damage_terrain_at(tx, ty, dmg, ignore_durability) == MaterialId::AIR {
+                destroyed.push((tx, ty));
+            }
+        }
+
+        // Apply the blasts of the explosions
+        for (x, y, blast) in detonations {
+            destroyed.extend(self.apply_blast(x, y, blast));
+        }
+
+        // Remove expired effects
+        let behaviors = &self.behaviors;
+        self.effects.retain(|eff| eff.age < behaviors.get(eff.effect_type).lifetime());
+
+        // Forward all collected sounds after the loop
+        for sound_path in sounds_to_play {
+            self.events.push(SimulationEvent::Sound { path: sound_path.to_string(), volume: 0.2 });
+        }
+
+        destroyed
+    }
+
+    // Damage the terrain around an explosion, according to the blast's radius and falloff.
+    // Returns the cells destroyed by the blast.
+    fn apply_blast(&mut self, x: f32, y: f32, blast: Blast) -> Vec<(usize, usize)> {
+        let radius = blast.radius * self.cell_size;
+        let query_rect = Rect::new(x - radius, y - radius, radius * 2.0, radius * 2.0);
+
+        // The blast can reach chunks that are not loaded yet
+        let (columns, rows) = self.cells_in(&query_rect);
+        self.load_chunks(self.chunks_of(columns, rows, 0));
+
+        let mut destroyed = Vec::new();
+        for candidate in self.terrain.query(query_rect) {
+            // Only damage solid cells within the circle
+            let distance = ((candidate.x - x).powi(2) + (candidate.y - y).powi(2)).sqrt();
+            let material = self.terrain.get(candidate.tx, candidate.ty).map_or(MaterialId::AIR, |cell| cell.material);
+            if distance > radius || material == MaterialId::AIR || self.materials.get(material).liquid {
+                continue;
+            }
+
+            // The damage decreases with the distance, and durability still applies
+            let damage = blast.damage * blast.falloff.factor(distance / radius);
+            if self.damage_terrain_at(candidate.tx, candidate.ty, damage, false) == MaterialId::AIR {
+                destroyed.push((candidate.tx, candidate.ty));
+            }
+        }
+
+        self.events.push(SimulationEvent::Explosion { x, y, radius });
+        self.events.push(SimulationEvent::Sound { path: "resources/sounds/stone.ogg".to_string(), volume: 0.5 });
+        destroyed
+    }
+
+    // Mark the given cells as changed
+    fn record_changes(&mut self, changed: Vec<(usize, usize)>) {
+        self.events.extend(changed.into_iter().map(|(x, y)| SimulationEvent::CellChanged { x, y }));
+    }
+
+    // Fill the air cells around a position with a material
+    pub fn pour(&mut self, x: f32, y: f32, material: MaterialId, radius: usize) {
+        let cx = (x / self.cell_size) as isize;
+        let cy = (y / self.cell_size) as isize;
+        let r = radius as isize;
+        let durability = self.materials.get(material).durability;
+
+        // Pouring can reach chunks that are not loaded yet
+        let columns = (cx - r).max(0) as usize..(cx + r + 1).max(0) as usize;
+        let rows = (cy - r).max(0) as usize..(cy + r + 1).max(0) as usize;
+        self.load_chunks(self.chunks_of(columns, rows, 0));
+
+        let mut changed = Vec::new();
+        for tx in cx - r..=cx + r {
+            for ty in cy - r..=cy + r {
+                // Stay within the terrain and the circle
+                if tx < 0 || ty < 0 || tx as usize >= self.terrain_width || ty as usize >= self.terrain_height
+                    || (tx - cx).pow(2) + (ty - cy).pow(2) > r * r {
+                    continue;
+                }
+                let (tx, ty) = (tx as usize, ty as usize);
+                if self.terrain.get(tx, ty).is_some_and(|cell| cell.material == MaterialId::AIR) {
+                    self.terrain.set(tx, ty, Cell { material, durability });
+                    changed.push((tx, ty));
+                }
+            }
+        }
+        self.record_changes(changed);
+    }
+
+    // Turn the chunks cut loose around destroyed cells into debris
+    fn detach_unsupported(&mut self, destroyed: &[(usize, usize)]) {
+
+        // Search chunks from the neighbours of every destroyed cell
+        let seeds: Vec<(usize, usize)> = destroyed.iter()
+            .flat_map(|&(x, y)| [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)])
+            .collect();
+
+        // The search stops at the chunks around the modified ones, so that the view does not change which chunks collapse
+        for chunks in self.terrain.modified_surroundings() {
+            self.load_chunks(chunks);
+        }
+        let islands = find_unsupported(&self.terrain, &self.materials, &seeds);
+
+        for island in islands {
+            self.events.extend(island.iter().map(|&(x, y)| SimulationEvent::CellChanged { x, y }));
+            self.debris.push(Debris::detach(&mut self.terrain, &island));
+        }
+    }
+
+    // Make the debris fall, and settle the ones that landed back into the terrain
+    fn update_debris(&mut self, dt: f32) {
+        let mut falling = Vec::with_capacity(self.debris.len());
+        for mut debris in std::mem::take(&mut self.debris) {
+            // The debris lands the same way whether the chunks below it were loaded by the view or not
+            let (columns, rows) = debris.reach(dt);
+            self.load_chunks(self.chunks_of(columns, rows, 0));
+
+            if debris.fall(&self.terrain, dt) {
+                let changed = debris.settle(&mut self.terrain);
+                self.record_changes(changed);
+            } else {
+                falling.push(debris);
+            }
+        }
+        self.debris = falling;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Overrides;
+
+    // A small world with the built-in definitions and every pass enabled
+    fn simulation(seed: i64) -> Simulation {
+        let config = Config { terrain_width: 160, terrain_height: 96, seed, falling_sand: true, collapse: true, ..Config::default() };
+        let materials = MaterialRegistry::default();
+        let layers = TerrainLayers::builtin(&materials).unwrap();
+        let behaviors = EffectRegistry::builtin(&Overrides::new()).unwrap();
+        Simulation::new(&config, None, layers, materials, behaviors)
+    }
+
+    // Every cell of the world, as `(material, durability)`
+    fn cells(simulation: &Simulation) -> Vec<(u8, f32)> {
+        simulation.to_grid().iter().flatten().map(|cell| (cell.material.0, cell.durability)).collect()
+    }
+
+    // Spawn every effect and pour some water, then run a few seconds
+    fn play(simulation: &mut Simulation) {
+        let effects: Vec<EffectId> = simulation.behaviors().ids().collect();
+        for (i, effect) in effects.into_iter().enumerate() {
+            simulation.spawn_effect(100.0 + 150.0 * i as f32, 200.0, effect);
+        }
+        let water = simulation.materials().id("water").unwrap();
+        simulation.pour(400.0, 100.0, water, 6);
+        for _ in 0..60 {
+            simulation.step(1.0 / 15.0);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_world() {
+        let (mut a, mut b) = (simulation(42), simulation(42));
+        let generated = cells(&a);
+        assert_eq!(generated, cells(&b));
+
+        play(&mut a);
+        play(&mut b);
+        assert_eq!(a.tick(), 60);
+        assert_ne!(cells(&a), generated);
+        assert_eq!(cells(&a), cells(&b));
+        assert_eq!(a.effects().len(), b.effects().len());
+        assert_eq!(a.debris().len(), b.debris().len());
+    }
+
+    #[test]
+    fn other_seed_gives_other_terrain() {
+        assert_ne!(cells(&simulation(1)), cells(&simulation(2)));
+    }
+
+    #[test]
+    fn view_does_not_change_outcome() {
+        let (mut a, mut b) = (simulation(7), simulation(7));
+        a.set_view(Rect::new(0.0, 0.0, 50.0, 50.0));
+
+        play(&mut a);
+        play(&mut b);
+        assert_eq!(cells(&a), cells(&b));
+    }
+
+    #[test]
+    fn sidescroller_mode_requires_ground_materials() {
+        let materials = MaterialRegistry::parse("[[material]]\nname = \"air\"\ncolor = [0, 0, 0]\n\n[[material]]\nname = \"stone\"\ncolor = [1, 1, 1]\n").unwrap();
+        let layers = TerrainLayers::parse("[[band]]\nbelow = 0.0\nmaterial = \"air\"\n\n[[band]]\nmaterial = \"stone\"\n", &materials).unwrap();
+        let config = Config { seed: 3, ..Config::default() };
+        let mut simulation = Simulation::new(&config, None, layers, materials, EffectRegistry::default());
+
+        let sidescroller = Config { mode: GenerationMode::Sidescroller, ..config };
+        assert!(simulation.reconfigure(&sidescroller).is_err());
+        assert_eq!(simulation.mode, GenerationMode::Field);
+    }
+
+    #[test]
+    fn generated_sand_settles() {
+        let materials = MaterialRegistry::default();
+        let layers = TerrainLayers::parse("[[band]]\nbelow = 0.0\nmaterial = \"air\"\n\n[[band]]\nmaterial = \"sand\"\n", &materials).unwrap();
+        let config = Config { seed: 5, falling_sand: true, ..Config::default() };
+        let mut simulation = Simulation::new(&config, None, layers, materials, EffectRegistry::default());
+        let sand = simulation.materials().id("sand").unwrap();
+        let floating = |simulation: &Simulation| {
+            simulation.to_grid().iter().any(|column| column.windows(2).any(|pair| pair[0].material == sand && pair[1].material == MaterialId::AIR))
+        };
+        assert!(floating(&simulation));
+
+        // The grains fall although no cell was modified
+        for _ in 0..200 {
+            simulation.step(1.0 / 15.0);
+        }
+        assert!(!floating(&simulation));
+    }
+}
@@ -0,0 +1,207 @@
+// -----------------------------------------------------------------------------
+// File: terrainimage.rs
+// Description: Conversion between images and the terrain grid: import of
+//              hand-drawn maps and export of the terrain to PNG.
+// Author(s): DIARRA Amara & SERRANO Jean-Léo
+// License: CC BY-NC 4.0
+// Created: October 16, 2026
+// Last modified: October 16, 2026
+// Version: 1.0
+// -----------------------------------------------------------------------------
+
+use clap::ValueEnum;
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::fmt;
+use std::ops::Range;
+
+use crate::cell::Cell;
+use crate::effect::Shape;
+use crate::layers::TerrainLayers;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::simulation::Simulation;
+
+// Largest number of pixels of a rendered image, keeping it within a few hundred megabytes
+const MAX_IMAGE_PIXELS: u64 = 1 << 26;
+
+/// Represents how the pixels of an image are turned into materials.
+///
+/// # Variants
+///
+/// - `Palette`: Each pixel takes the material whose color is the closest; transparent pixels are air.
+/// - `Grayscale`: The brightness of each pixel is mapped to `[-1, 1]` and split into materials with the
+///   same bands as the noise generation, so with the default bands white is rock and black is air.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Debug)]
+pub enum ImageMode {
+    Palette,
+    Grayscale,
+}
+
+impl fmt::Display for ImageMode {
+
+    /// Formats the `ImageMode` enum as a string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageMode::Palette => write!(f, "palette"),
+            ImageMode::Grayscale => write!(f, "grayscale"),
+        }
+    }
+}
+
+/// Builds a terrain grid from an image file.
+///
+/// The image is resampled (nearest pixel) to the terrain dimensions, so an image drawn at one
+/// pixel per cell maps exactly onto the grid. Every cell gets the durability of its material, or of its band.
+///
+/// # Parameters
+/// - `path`: The image file (PNG).
+/// - `terrain_width`: The width of the terrain, in cells.
+/// - `terrain_height`: The height of the terrain, in cells.
+/// - `materials`: The material registry, giving the colors of the palette mode.
+/// - `layers`: The terrain layers, giving the bands of the grayscale mode.
+/// - `mode`: How pixels are turned into materials.
+///
+/// # Returns
+/// The terrain grid, indexed as `terrain[x][y]`, or an error if the image cannot be read.
+pub fn import_terrain_image(path: &str, terrain_width: usize, terrain_height: usize, materials: &MaterialRegistry, layers: &TerrainLayers, mode: ImageMode) -> Result<Vec<Vec<Cell>>, String> {
+    let image = image::open(path).map_err(|e| format!("cannot read {}: {}", path, e))?.to_rgba8();
+    if image.width() == 0 || image.height() == 0 {
+        return Err(format!("{}: empty image", path));
+    }
+
+    let mut terrain = vec![Vec::with_capacity(terrain_height); terrain_width];
+    for (x, column) in terrain.iter_mut().enumerate() {
+        for y in 0..terrain_height {
+            // Nearest pixel of the cell
+            let px = (x * image.width() as usize / terrain_width) as u32;
+            let py = (y * image.height() as usize / terrain_height) as u32;
+            let [r, g, b, a] = image.get_pixel(px, py).0;
+
+            let cell = match mode {
+                ImageMode::Palette => {
+                    let material = if a < 128 { MaterialId::AIR } else { closest_material(materials, [r, g, b]) };
+                    Cell { material, durability: materials.get(material).durability }
+                }
+                ImageMode::Grayscale => {
+                    let brightness = (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) / 255.0;
+                    layers.cell(brightness * 2.0 - 1.0)
+                }
+            };
+            column.push(cell);
+        }
+    }
+
+    Ok(terrain)
+}
+
+// Find the material whose color is the closest to a pixel
+fn closest_material(materials: &MaterialRegistry, color: [u8; 3]) -> MaterialId {
+    let distance = |other: [u8; 3]| -> i32 {
+        (0..3).map(|i| (color[i] as i32 - other[i] as i32).pow(2)).sum()
+    };
+    (0..materials.len())
+        .map(|i| MaterialId(i as u8))
+        .min_by_key(|&id| distance(materials.get(id).color))
+        .unwrap_or(MaterialId::AIR)
+}
+
+/// Renders a part of the terrain to an image, with the same material colors as the game.
+///
+/// # Parameters
+/// - `simulation`: The simulation whose terrain is rendered.
+/// - `(columns, rows)`: The columns and rows of the cells rendered, e.g. the whole world or the cells in view.
+/// - `scale`: The size of a cell in the image, in pixels (1 for one pixel per cell, or the cell size
+///   to match the rendered window).
+/// - `overlays`: Whether the falling debris and the active effects are drawn over the terrain.
+///
+/// # Returns
+/// The rendered image, or an error if it would be too large.
+pub fn render_terrain_image(simulation: &Simulation, (columns, rows): (Range<usize>, Range<usize>), scale: u32, overlays: bool) -> Result<RgbaImage, String> {
+    let scale = scale.max(1);
+    let materials = simulation.materials();
+    let too_large = || format!("a {}x{} cell image at {} pixels per cell is too large", columns.len(), rows.len(), scale);
+    let size = |cells: usize| u32::try_from(cells).ok().and_then(|cells| cells.checked_mul(scale));
+    let (Some(width), Some(height)) = (size(columns.len()), size(rows.len())) else {
+        return Err(too_large());
+    };
+    if width as u64 * height as u64 > MAX_IMAGE_PIXELS {
+        return Err(too_large());
+    }
+    let color = |material: MaterialId| {
+        let [r, g, b] = materials.get(material).color;
+        Rgba([r, g, b, 255])
+    };
+
+    // One block of `scale` pixels per cell
+    let terrain = simulation.grid(columns.clone(), rows.clone());
+    let mut image = RgbaImage::from_fn(width, height, |px, py| {
+        color(terrain[(px / scale) as usize][(py / scale) as usize].material)
+    });
+    if !overlays {
+        return Ok(image);
+    }
+
+    // Position of the rendered cells in the image
+    let (origin_x, origin_y) = (columns.start as f32 * scale as f32, rows.start as f32 * scale as f32);
+
+    // Falling debris
+    for debris in simulation.debris() {
+        for (dx, dy, cell) in &debris.cells {
+            let x = ((debris.x + dx) as f32 * scale as f32 - origin_x) as i64;
+            let y = ((debris.y + *dy as f32) * scale as f32 - origin_y) as i64;
+            fill(&mut image, x, y, x + scale as i64, y + scale as i64, |_, _| true, color(cell.material));
+        }
+    }
+
+    // Effects, sized in cells like on screen
+    let to_image = scale as f32 / simulation.cell_size();
+    for effect in simulation.effects() {
+        let visual = simulation.behaviors().get(effect.effect_type).visual();
+        let [r, g, b] = visual.color;
+        let (cx, cy) = (effect.position.0 * to_image - origin_x, effect.position.1 * to_image - origin_y);
+        let angle = if visual.rotate { effect.direction } else { 0.0 };
+        let (half_w, half_h) = match visual.shape {
+            Shape::Circle { radius } => (radius * scale as f32, radius * scale as f32),
+            Shape::Rectangle { width, height } => (width * scale as f32 / 2.0, height * scale as f32 / 2.0),
+        };
+        let extent = half_w.hypot(half_h).ceil() as i64;
+        let inside = |px: f32, py: f32| {
+            // Express the pixel in the frame of the effect
+            let (dx, dy) = (px - cx, py - cy);
+            let (u, v) = (dx * angle.cos() + dy * angle.sin(), -dx * angle.sin() + dy * angle.cos());
+            match visual.shape {
+                Shape::Circle { .. } => u * u + v * v <= half_w * half_w,
+                Shape::Rectangle { .. } => u.abs() <= half_w && v.abs() <= half_h,
+            }
+        };
+        let (x, y) = (cx as i64, cy as i64);
+        fill(&mut image, x - extent, y - extent, x + extent + 1, y + extent + 1, inside, Rgba([r, g, b, 255]));
+    }
+
+    Ok(image)
+}
+
+/// Renders a part of the terrain and writes it to a PNG file.
+///
+/// # Parameters
+/// - `simulation`: The simulation whose terrain is exported.
+/// - `path`: The PNG file to write.
+/// - `cells`: The columns and rows of the cells exported.
+/// - `scale`: The size of a cell in the image, in pixels.
+/// - `overlays`: Whether the falling debris and the active effects are drawn over the terrain.
+pub fn export_terrain_image(simulation: &Simulation, path: &str, cells: (Range<usize>, Range<usize>), scale: u32, overlays: bool) -> Result<(), String> {
+    render_terrain_image(simulation, cells, scale, overlays)?
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(|e| format!("cannot write {}: {}", path, e))
+}
+
+// Fill the pixels of a rectangle (clipped to the image) accepted by `inside`, tested at their centre
+fn fill(image: &mut RgbaImage, x0: i64, y0: i64, x1: i64, y1: i64, inside: impl Fn(f32, f32) -> bool, color: Rgba<u8>) {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    for py in y0.max(0)..y1.min(height) {
+        for px in x0.max(0)..x1.min(width) {
+            if inside(px as f32 + 0.5, py as f32 + 0.5) {
+                image.put_pixel(px as u32, py as u32, color);
+            }
+        }
+    }
+}